@@ -0,0 +1,109 @@
+//! Standard base64 with padding, per RFC 4648, e.g. to embed a file in config or paste it into a
+//! text channel.
+
+use thiserror::Error;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, Error)]
+pub enum Base64Error {
+    #[error("invalid base64 character {0:?}")]
+    InvalidCharacter(char),
+    #[error("base64 length is not a multiple of 4")]
+    InvalidLength,
+    #[error("misplaced base64 padding")]
+    MisplacedPadding,
+}
+
+/// Encodes `bytes` as base64, padded to a multiple of 4 characters, without line breaks.
+pub fn encode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let mut group = [0; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+        for k in 0..4 {
+            if k <= chunk.len() {
+                text.push(char::from(ALPHABET[(bits >> (18 - 6 * k)) as usize & 0x3f]));
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Decodes base64 text. Whitespace, including line breaks, is ignored.
+pub fn decode(text: &str) -> Result<Vec<u8>, Base64Error> {
+    let digits: Vec<u8> = text.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(4) {
+        return Err(Base64Error::InvalidLength);
+    }
+    let mut bytes = Vec::with_capacity(digits.len() / 4 * 3);
+    for (n, chunk) in digits.chunks_exact(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && n + 1 != digits.len() / 4) {
+            return Err(Base64Error::MisplacedPadding);
+        }
+        let mut bits = 0;
+        for &c in &chunk[..4 - padding] {
+            let value = ALPHABET
+                .iter()
+                .position(|&digit| digit == c)
+                .ok_or(Base64Error::InvalidCharacter(char::from(c)))?;
+            bits = (bits << 6) | u32::try_from(value).unwrap();
+        }
+        bits <<= 6 * padding;
+        bytes.extend_from_slice(&bits.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Base64Error, decode, encode};
+    use crate::bluray::{MovieObjectFile, testing};
+
+    #[test]
+    fn matches_rfc_4648_vectors() {
+        for (data, expected) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"fooba", "Zm9vYmE="),
+            (b"foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(encode(data), expected);
+            assert_eq!(decode(expected).unwrap(), data);
+        }
+        assert!(matches!(decode("Zm9"), Err(Base64Error::InvalidLength)));
+        assert!(matches!(
+            decode("Zg==Zm9v"),
+            Err(Base64Error::MisplacedPadding)
+        ));
+        assert!(matches!(
+            decode("Zm9!"),
+            Err(Base64Error::InvalidCharacter('!'))
+        ));
+    }
+
+    #[test]
+    fn movie_object_file_round_trips() {
+        let bytes = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "JUMP_OBJECT 1"]]);
+        let text = encode(&bytes);
+        // Line breaks, e.g. from pasting, are ignored.
+        let wrapped: String = text
+            .as_bytes()
+            .chunks(16)
+            .map(|line| format!("{}\n", std::str::from_utf8(line).unwrap()))
+            .collect();
+        let decoded = decode(&wrapped).unwrap();
+        assert_eq!(decoded, bytes);
+        assert_eq!(
+            MovieObjectFile::from_bytes(&decoded).unwrap().serialize(),
+            bytes
+        );
+    }
+}
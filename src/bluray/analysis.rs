@@ -0,0 +1,559 @@
+use super::{
+    Branch, Command, Compare, MovieObject, MovieObjectFile, NavigationCommand, Operand,
+    OperandCount, PatchAction, RegionFindingKind, Set,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Whether `commands` look like a "wrong region" screen, i.e. they mostly just play something and
+/// then stop playback or jump to a title. Nops are ignored.
+fn is_probable_error_section(commands: &[NavigationCommand]) -> bool {
+    let commands: Vec<_> = commands
+        .iter()
+        .map(|command| command.command)
+        .filter(|command| !matches!(command, Command::Branch(Branch::Nop)))
+        .collect();
+    let Some(play) = commands.iter().position(|command| {
+        matches!(
+            command,
+            Command::Branch(Branch::PlayList | Branch::PlayItem | Branch::PlayMark)
+        )
+    }) else {
+        return false;
+    };
+    let ends_playback = commands[play..].iter().any(|command| {
+        matches!(
+            command,
+            Command::Branch(Branch::Terminate | Branch::JumpTitle)
+        )
+    });
+    let relevant = commands
+        .iter()
+        .filter(|command| {
+            matches!(
+                command,
+                Command::Branch(
+                    Branch::PlayList
+                        | Branch::PlayItem
+                        | Branch::PlayMark
+                        | Branch::Terminate
+                        | Branch::JumpTitle
+                )
+            )
+        })
+        .count();
+    ends_playback && relevant * 2 >= commands.len()
+}
+
+impl MovieObject {
+    /// Whether this movie object looks like a "wrong region" screen, i.e. it mostly just plays
+    /// something and then stops playback or jumps to a title.
+    pub fn is_probable_error_screen(&self) -> bool {
+        is_probable_error_section(&self.navigation_commands)
+    }
+}
+
+/// A compare and the navigation command it guards, which only runs if the comparison is true.
+#[derive(Clone, Copy, Debug)]
+pub struct Gate {
+    pub movie_object_index: u16,
+    pub compare_index: u16,
+    pub guarded_index: u16,
+    /// The movie object the guarded command jumps to or calls, if any.
+    pub target: Option<u16>,
+}
+
+/// Which outcome of a gate's compare lets the region or country check pass.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GatePolarity {
+    /// The check passes when the comparison is true, i.e. when the guarded command runs.
+    PassesWhenTrue,
+    /// The check passes when the comparison is false, i.e. when the guarded command is skipped.
+    PassesWhenFalse,
+}
+
+/// A region compare whose other side is a GPR computed by arithmetic at runtime rather than
+/// loaded from an immediate, so rewriting PSR20 to a single value may not neutralize it.
+#[derive(Clone, Copy, Debug)]
+pub struct ComputedGate {
+    pub movie_object_index: u16,
+    pub compare_index: u16,
+    /// The GPR that the region value is compared against.
+    pub gpr: u16,
+    /// The last arithmetic command that computed the compared value.
+    pub arithmetic_index: u16,
+}
+
+/// How a navigation command uses a register operand.
+#[derive(Clone, Copy, Debug)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+/// A navigation command that uses a GPR.
+#[derive(Clone, Copy, Debug)]
+pub struct GprReference {
+    pub movie_object_index: u16,
+    pub navigation_command_index: u16,
+    pub access: Access,
+}
+
+impl MovieObjectFile {
+    /// Finds every navigation command that uses each GPR, in order.
+    pub fn gpr_references(&self) -> BTreeMap<u16, Vec<GprReference>> {
+        let mut references: BTreeMap<u16, Vec<GprReference>> = BTreeMap::new();
+        for (i, object) in (0..).zip(self.movie_objects.movie_objects.iter()) {
+            for (j, command) in (0..).zip(object.navigation_commands.iter()) {
+                let (destination, source) = match (command.command, command.operand_count) {
+                    (_, OperandCount::None) => continue,
+                    (Command::Set(Set::Move), _) => (Access::Write, Access::Read),
+                    (Command::Set(Set::Swap), _) => (Access::ReadWrite, Access::ReadWrite),
+                    (Command::Set(Set::Rnd), _) => (Access::Write, Access::Read),
+                    (
+                        Command::Set(
+                            Set::Add
+                            | Set::Sub
+                            | Set::Mul
+                            | Set::Div
+                            | Set::Mod
+                            | Set::And
+                            | Set::Or
+                            | Set::Xor
+                            | Set::Bitset
+                            | Set::Bitclr
+                            | Set::ShiftLeft
+                            | Set::ShiftRight,
+                        ),
+                        _,
+                    ) => (Access::ReadWrite, Access::Read),
+                    _ => (Access::Read, Access::Read),
+                };
+                let mut operands = vec![(command.destination, destination)];
+                if let OperandCount::DestinationAndSource = command.operand_count {
+                    operands.push((command.source, source));
+                }
+                for (operand, access) in operands {
+                    if let Operand::Gpr(gpr) = operand {
+                        references.entry(gpr).or_default().push(GprReference {
+                            movie_object_index: i,
+                            navigation_command_index: j,
+                            access,
+                        });
+                    }
+                }
+            }
+        }
+        references
+    }
+
+    /// Finds the gate starting at the given navigation command: the first compare at or after it,
+    /// together with the command immediately after that compare.
+    pub fn gate(&self, movie_object_index: u16, navigation_command_index: u16) -> Option<Gate> {
+        let commands = &self
+            .movie_objects
+            .movie_objects
+            .get(usize::from(movie_object_index))?
+            .navigation_commands;
+        let compare = commands
+            .iter()
+            .skip(usize::from(navigation_command_index))
+            .position(|command| matches!(command.command, Command::Compare(_)))?
+            + usize::from(navigation_command_index);
+        let guarded = commands.get(compare + 1)?;
+        let target = match (guarded.command, guarded.destination) {
+            (
+                Command::Branch(Branch::JumpObject | Branch::CallObject),
+                Operand::Immediate(target),
+            ) => u16::try_from(target)
+                .ok()
+                .filter(|&target| usize::from(target) < self.movie_objects.movie_objects.len()),
+            _ => None,
+        };
+        Some(Gate {
+            movie_object_index,
+            compare_index: u16::try_from(compare).unwrap(),
+            guarded_index: u16::try_from(compare + 1).unwrap(),
+            target,
+        })
+    }
+
+    /// Whether the gate's guarded command jumps to a probable "wrong region" screen, i.e. the
+    /// comparison being true means the check failed.
+    pub fn gate_fails_to_error_screen(&self, gate: &Gate) -> bool {
+        gate.target.is_some_and(|target| {
+            self.movie_objects.movie_objects[usize::from(target)].is_probable_error_screen()
+        })
+    }
+
+    /// Whether continuing at navigation command `index` of the movie object leads straight to a
+    /// "wrong region" screen: a jump or call to a movie object that looks like one, or a section
+    /// of the same movie object that plays something and then stops playback. A `GoTo` is
+    /// followed to the section it jumps to. A section ends at the first command that stops
+    /// playback or leaves it.
+    fn path_fails(&self, movie_object_index: u16, index: usize) -> bool {
+        let commands =
+            &self.movie_objects.movie_objects[usize::from(movie_object_index)].navigation_commands;
+        let Some(command) = commands.get(index) else {
+            return false;
+        };
+        let start = match (command.command, command.destination) {
+            (
+                Command::Branch(Branch::JumpObject | Branch::CallObject),
+                Operand::Immediate(target),
+            ) => {
+                return self
+                    .movie_objects
+                    .movie_objects
+                    .get(usize::try_from(target).unwrap())
+                    .is_some_and(MovieObject::is_probable_error_screen);
+            }
+            (Command::Branch(Branch::GoTo), Operand::Immediate(target)) => {
+                usize::try_from(target).unwrap()
+            }
+            _ => index,
+        };
+        let Some(section) = commands.get(start..) else {
+            return false;
+        };
+        // The section ends where playback stops or control leaves it.
+        let end = section
+            .iter()
+            .position(|command| {
+                matches!(
+                    command.command,
+                    Command::Branch(
+                        Branch::Terminate | Branch::JumpTitle | Branch::JumpObject | Branch::GoTo
+                    )
+                )
+            })
+            .map_or(section.len(), |end| end + 1);
+        is_probable_error_section(&section[..end])
+    }
+
+    /// Works out which outcome of the gate's compare lets the check pass. Where its two paths
+    /// lead decides first: if exactly one of them leads to a "wrong region" screen, the other
+    /// one passes. Otherwise, a compare against an immediate is taken to check for the value the
+    /// disc expects, so `EQ` and `BC` pass when true and `NE` passes when false. Returns `None`
+    /// if neither tells, e.g. for `GT`, or if both paths lead to a "wrong region" screen.
+    pub fn gate_polarity(&self, gate: &Gate) -> Option<GatePolarity> {
+        let commands = &self.movie_objects.movie_objects[usize::from(gate.movie_object_index)]
+            .navigation_commands;
+        let true_fails = self.path_fails(gate.movie_object_index, usize::from(gate.guarded_index));
+        let false_fails =
+            self.path_fails(gate.movie_object_index, usize::from(gate.guarded_index) + 1);
+        match (true_fails, false_fails) {
+            (true, false) => return Some(GatePolarity::PassesWhenFalse),
+            (false, true) => return Some(GatePolarity::PassesWhenTrue),
+            (true, true) => return None,
+            (false, false) => {}
+        }
+        let compare = &commands[usize::from(gate.compare_index)];
+        if !matches!(compare.operand_count, OperandCount::DestinationAndSource)
+            || !matches!(
+                (compare.destination, compare.source),
+                (Operand::Immediate(_), _) | (_, Operand::Immediate(_))
+            )
+        {
+            return None;
+        }
+        match compare.command {
+            Command::Compare(Compare::Eq | Compare::Bc) => Some(GatePolarity::PassesWhenTrue),
+            Command::Compare(Compare::Ne) => Some(GatePolarity::PassesWhenFalse),
+            _ => None,
+        }
+    }
+
+    /// The patch that makes the gate always take its passing path, as the index of the
+    /// navigation command to patch and the action, or `None` if [`Self::gate_polarity`] can't
+    /// tell which path passes. If the check passes when true, the compare becomes a nop so the
+    /// guarded command, e.g. a `GoTo` to the passing section, always runs. Otherwise the compare
+    /// becomes a `GoTo` past the guarded command, or the guarded command a nop if it's the last
+    /// command.
+    pub fn force_pass_patch(&self, gate: &Gate) -> Option<(u16, PatchAction)> {
+        let len = self.movie_objects.movie_objects[usize::from(gate.movie_object_index)]
+            .navigation_commands
+            .len();
+        Some(match self.gate_polarity(gate)? {
+            GatePolarity::PassesWhenTrue => (gate.compare_index, PatchAction::Nop),
+            GatePolarity::PassesWhenFalse if usize::from(gate.guarded_index) + 1 < len => (
+                gate.compare_index,
+                PatchAction::GoTo(gate.guarded_index + 1),
+            ),
+            GatePolarity::PassesWhenFalse => (gate.guarded_index, PatchAction::Nop),
+        })
+    }
+
+    /// The movie objects that running `start` can lead to, including `start` itself: those it
+    /// jumps to or calls with an immediate, and so on. Every such command counts, whether or not
+    /// it's behind a compare.
+    pub fn reachable_objects(&self, start: u16) -> BTreeSet<u16> {
+        let mut reached = BTreeSet::new();
+        let mut pending = vec![start];
+        while let Some(index) = pending.pop() {
+            let Some(object) = self.movie_objects.movie_objects.get(usize::from(index)) else {
+                continue;
+            };
+            if !reached.insert(index) {
+                continue;
+            }
+            for command in &object.navigation_commands {
+                if let (
+                    Command::Branch(Branch::JumpObject | Branch::CallObject),
+                    OperandCount::DestinationOnly | OperandCount::DestinationAndSource,
+                    Operand::Immediate(target),
+                ) = (command.command, command.operand_count, command.destination)
+                    && let Ok(target) = u16::try_from(target)
+                {
+                    pending.push(target);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Finds compares of PSR20, directly or via a derived GPR, against a GPR whose value was
+    /// computed with set arithmetic, e.g. `ADD` or `AND`, earlier in the same movie object.
+    /// Moves between GPRs are followed back to the arithmetic.
+    pub fn computed_gates(&self) -> Vec<ComputedGate> {
+        let mut gates = vec![];
+        for finding in self.region_checks() {
+            let region_side = match finding.kind {
+                RegionFindingKind::DirectRead if finding.psr == 20 => Operand::Psr(20),
+                RegionFindingKind::IndirectViaGpr(gpr) if finding.psr == 20 => Operand::Gpr(gpr),
+                _ => continue,
+            };
+            let commands = &self.movie_objects.movie_objects
+                [usize::from(finding.movie_object_index)]
+            .navigation_commands;
+            let compare = &commands[usize::from(finding.navigation_command_index)];
+            if !matches!(compare.command, Command::Compare(_)) {
+                continue;
+            }
+            let other = match (compare.destination, region_side) {
+                (Operand::Psr(a), Operand::Psr(b)) if a == b => compare.source,
+                (Operand::Gpr(a), Operand::Gpr(b)) if a == b => compare.source,
+                _ => compare.destination,
+            };
+            let Operand::Gpr(compared_gpr) = other else {
+                continue;
+            };
+            let mut gpr = compared_gpr;
+            let mut arithmetic = None;
+            for (k, command) in (0..finding.navigation_command_index)
+                .zip(commands.iter())
+                .rev()
+            {
+                let Command::Set(set) = command.command else {
+                    continue;
+                };
+                if matches!(command.operand_count, OperandCount::None)
+                    || !matches!(command.destination, Operand::Gpr(num) if num == gpr)
+                {
+                    continue;
+                }
+                match (set, command.operand_count, command.source) {
+                    (Set::Move, OperandCount::DestinationAndSource, Operand::Gpr(source)) => {
+                        gpr = source;
+                    }
+                    (Set::Move, _, _) => break,
+                    _ => {
+                        arithmetic = Some(k);
+                        break;
+                    }
+                }
+            }
+            if let Some(arithmetic_index) = arithmetic {
+                gates.push(ComputedGate {
+                    movie_object_index: finding.movie_object_index,
+                    compare_index: finding.navigation_command_index,
+                    gpr: compared_gpr,
+                    arithmetic_index,
+                });
+            }
+        }
+        gates
+    }
+
+    /// The indices of the navigation commands that make up a gate: the commands that load and
+    /// modify a compared GPR, e.g. from PSR19 or PSR20, followed by the compare and the guarded
+    /// command.
+    pub fn gate_commands(&self, gate: &Gate) -> Vec<u16> {
+        let commands = &self.movie_objects.movie_objects[usize::from(gate.movie_object_index)]
+            .navigation_commands;
+        let compare = &commands[usize::from(gate.compare_index)];
+        let mut feeding = vec![];
+        for operand in [compare.destination, compare.source] {
+            let Operand::Gpr(gpr) = operand else {
+                continue;
+            };
+            // Walk back to the move that loaded the GPR, collecting anything that modified it.
+            for (k, command) in (0..gate.compare_index).zip(commands.iter()).rev() {
+                let Command::Set(set) = command.command else {
+                    continue;
+                };
+                if matches!(command.operand_count, OperandCount::None)
+                    || !matches!(command.destination, Operand::Gpr(num) if num == gpr)
+                {
+                    continue;
+                }
+                feeding.push(k);
+                if set == Set::Move {
+                    break;
+                }
+            }
+        }
+        feeding.sort_unstable();
+        feeding.dedup();
+        feeding.extend([gate.compare_index, gate.guarded_index]);
+        feeding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GatePolarity;
+    use crate::bluray::testing::{assembly, file};
+
+    /// Applies the force-pass patch for the gate starting at the navigation command.
+    fn force_pass(
+        file: &mut crate::bluray::MovieObjectFile,
+        movie_object_index: u16,
+        navigation_command_index: u16,
+    ) {
+        let gate = file
+            .gate(movie_object_index, navigation_command_index)
+            .unwrap();
+        let (index, action) = file.force_pass_patch(&gate).unwrap();
+        file.apply_patch(movie_object_index, index, action).unwrap();
+    }
+
+    #[test]
+    fn force_pass_skips_goto_to_inline_error_section() {
+        let mut file = file(&[&[
+            "MOV gpr1, psr20",
+            "NE gpr1, 2",
+            "GOTO 5",
+            "PLAY_PL 1",
+            "JUMP_OBJECT 0",
+            "PLAY_PL 99",
+            "TERMINATE",
+        ]]);
+        let gate = file.gate(0, 0).unwrap();
+        assert_eq!(
+            file.gate_polarity(&gate),
+            Some(GatePolarity::PassesWhenFalse)
+        );
+        force_pass(&mut file, 0, 0);
+        assert_eq!(
+            assembly(&file, 0),
+            [
+                "MOV gpr1, psr20",
+                "GOTO 3",
+                "GOTO 5",
+                "PLAY_PL 1",
+                "JUMP_OBJECT 0",
+                "PLAY_PL 99",
+                "TERMINATE",
+            ]
+        );
+    }
+
+    #[test]
+    fn force_pass_makes_goto_to_passing_section_unconditional() {
+        let mut file = file(&[&[
+            "EQ psr20, 2",
+            "GOTO 4",
+            "PLAY_PL 99",
+            "TERMINATE",
+            "PLAY_PL 1",
+            "JUMP_OBJECT 0",
+        ]]);
+        let gate = file.gate(0, 0).unwrap();
+        assert_eq!(
+            file.gate_polarity(&gate),
+            Some(GatePolarity::PassesWhenTrue)
+        );
+        force_pass(&mut file, 0, 0);
+        assert_eq!(
+            assembly(&file, 0),
+            [
+                "NOP",
+                "GOTO 4",
+                "PLAY_PL 99",
+                "TERMINATE",
+                "PLAY_PL 1",
+                "JUMP_OBJECT 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn error_screen_target_overrides_compare_condition() {
+        // Rejecting region A rather than requiring region B: EQ, but true means failure.
+        let mut file = file(&[
+            &["EQ psr20, 1", "JUMP_OBJECT 1", "PLAY_PL 1"],
+            &["PLAY_PL 99", "TERMINATE"],
+        ]);
+        let gate = file.gate(0, 0).unwrap();
+        assert_eq!(
+            file.gate_polarity(&gate),
+            Some(GatePolarity::PassesWhenFalse)
+        );
+        force_pass(&mut file, 0, 0);
+        assert_eq!(assembly(&file, 0), ["GOTO 2", "JUMP_OBJECT 1", "PLAY_PL 1"]);
+    }
+
+    #[test]
+    fn last_guarded_command_is_replaced_with_nop() {
+        let mut file = file(&[&["NE psr20, 2", "MOV gpr2, 1"]]);
+        force_pass(&mut file, 0, 0);
+        assert_eq!(assembly(&file, 0), ["NE psr20, 2", "NOP"]);
+    }
+
+    #[test]
+    fn unknown_polarity_is_not_guessed() {
+        let file = file(&[&["GT psr20, 1", "MOV gpr2, 1", "PLAY_PL 1"]]);
+        let gate = file.gate(0, 0).unwrap();
+        assert_eq!(file.gate_polarity(&gate), None);
+        assert!(file.force_pass_patch(&gate).is_none());
+    }
+
+    #[test]
+    fn both_paths_failing_is_not_guessed() {
+        let file = file(&[
+            &["NE psr20, 2", "JUMP_OBJECT 1", "JUMP_OBJECT 1"],
+            &["PLAY_PL 99", "TERMINATE"],
+        ]);
+        let gate = file.gate(0, 0).unwrap();
+        assert_eq!(file.gate_polarity(&gate), None);
+    }
+
+    #[test]
+    fn computed_gates_follow_moves_back_to_arithmetic() {
+        let file = file(&[
+            &[
+                "MOV gpr2, 1",
+                "ADD gpr2, 1",
+                "MOV gpr3, gpr2",
+                "EQ psr20, gpr3",
+                "JUMP_OBJECT 1",
+            ],
+            // A plain move of an immediate isn't computed.
+            &["MOV gpr3, 2", "EQ psr20, gpr3", "JUMP_OBJECT 0"],
+        ]);
+        let gates: Vec<_> = file
+            .computed_gates()
+            .iter()
+            .map(|gate| {
+                (
+                    gate.movie_object_index,
+                    gate.compare_index,
+                    gate.gpr,
+                    gate.arithmetic_index,
+                )
+            })
+            .collect();
+        assert_eq!(gates, [(0, 3, 3, 1)]);
+    }
+}
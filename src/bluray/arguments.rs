@@ -0,0 +1,277 @@
+use super::{Branch, Command, NavigationCommand, RawCommand, Set};
+
+/// Decoded operands for commands that pack several fields into their operands instead of using
+/// them as plain registers or immediates.
+#[derive(Clone, Copy, Debug)]
+pub enum Arguments {
+    ButtonPage {
+        /// The button to select, if the button flag is set.
+        button: Option<Id>,
+        /// The page to show, if the page flag is set.
+        page: Option<Id>,
+        /// Whether the out effects of the current page should be skipped.
+        skip_out_effects: bool,
+    },
+    EnableButton {
+        button: Id,
+    },
+    DisableButton {
+        button: Id,
+    },
+    NvTimer {
+        /// The movie object to jump to when the timer expires.
+        movie_object: Id,
+        /// The timeout in seconds.
+        timeout: Id,
+    },
+    /// Jumps to a play item within the current playlist.
+    LinkItem {
+        item: Id,
+    },
+    /// Jumps to a playlist mark within the current playlist.
+    LinkMark {
+        mark: Id,
+    },
+    /// Each stream is `None` if its flag is clear, i.e. the current selection is kept.
+    SetStream {
+        primary_audio: Option<Id>,
+        pg_textst: Option<Id>,
+        /// Whether PG and TextST subtitles are displayed. Unlike the streams, this is always set.
+        pg_textst_displayed: bool,
+        interactive_graphics: Option<Id>,
+        angle: Option<Id>,
+    },
+}
+
+/// An ID that is either encoded directly in the command or read from a GPR when the command runs.
+/// Unlike regular register operands, any flag bits packed alongside the ID are always literal.
+#[derive(Clone, Copy, Debug)]
+pub enum Id {
+    Literal(u32),
+    Gpr(u16),
+}
+
+impl Id {
+    fn new(is_immediate: bool, value: u32) -> Id {
+        if is_immediate {
+            Id::Literal(value)
+        } else {
+            Id::Gpr((value & 0xfff) as u16)
+        }
+    }
+}
+
+impl std::fmt::Display for Id {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Id::Literal(value) => write!(fmt, "{value}"),
+            Id::Gpr(num) => write!(fmt, "gpr{num}"),
+        }
+    }
+}
+
+impl std::fmt::Display for Arguments {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arguments::ButtonPage {
+                button,
+                page,
+                skip_out_effects,
+            } => {
+                write!(fmt, "ButtonPage")?;
+                if let Some(page) = page {
+                    write!(fmt, " page={page}")?;
+                }
+                if let Some(button) = button {
+                    write!(fmt, " button={button}")?;
+                }
+                if *skip_out_effects {
+                    write!(fmt, " skip_out_effects")?;
+                }
+                Ok(())
+            }
+            Arguments::EnableButton { button } => write!(fmt, "EnableButton button={button}"),
+            Arguments::DisableButton { button } => write!(fmt, "DisableButton button={button}"),
+            Arguments::NvTimer {
+                movie_object,
+                timeout,
+            } => {
+                write!(fmt, "NvTimer movie_object={movie_object} timeout={timeout}")?;
+                if let Id::Literal(_) = timeout {
+                    write!(fmt, "s")?;
+                }
+                Ok(())
+            }
+            Arguments::LinkItem { item } => write!(fmt, "LinkItem item={item}"),
+            Arguments::LinkMark { mark } => write!(fmt, "LinkMark mark={mark}"),
+            Arguments::SetStream {
+                primary_audio,
+                pg_textst,
+                pg_textst_displayed,
+                interactive_graphics,
+                angle,
+            } => {
+                let selection = |stream: &Option<Id>| {
+                    stream.map_or_else(|| "keep".to_string(), |id| id.to_string())
+                };
+                write!(
+                    fmt,
+                    "SetStream primary_audio={} pg_textst={} pg_textst_display={} ig={} angle={}",
+                    selection(primary_audio),
+                    selection(pg_textst),
+                    if *pg_textst_displayed { "on" } else { "off" },
+                    selection(interactive_graphics),
+                    selection(angle)
+                )
+            }
+        }
+    }
+}
+
+impl NavigationCommand {
+    /// Decodes the operands of commands with a command-specific operand layout. Returns `None` for
+    /// commands whose operands are plain registers or immediates.
+    pub fn arguments(&self) -> Option<Arguments> {
+        // Based on the operand handling in libbluray's HDMV VM.
+        let RawCommand {
+            destination,
+            source,
+            destination_is_immediate_value,
+            source_is_immediate_value,
+            ..
+        } = self.raw();
+
+        match self.command {
+            Command::Set(Set::ButtonPage) => Some(Arguments::ButtonPage {
+                button: (destination & 0x80000000 != 0)
+                    .then(|| Id::new(destination_is_immediate_value, destination & 0xffff)),
+                page: (source & 0x80000000 != 0)
+                    .then(|| Id::new(source_is_immediate_value, source & 0xff)),
+                skip_out_effects: source & 0x40000000 != 0,
+            }),
+            Command::Set(Set::EnableButton) => Some(Arguments::EnableButton {
+                button: Id::new(destination_is_immediate_value, destination & 0xffff),
+            }),
+            Command::Set(Set::DisableButton) => Some(Arguments::DisableButton {
+                button: Id::new(destination_is_immediate_value, destination & 0xffff),
+            }),
+            Command::Set(Set::SetNVTimer) => Some(Arguments::NvTimer {
+                movie_object: Id::new(destination_is_immediate_value, destination & 0xffff),
+                timeout: Id::new(source_is_immediate_value, source & 0xffff),
+            }),
+            Command::Branch(Branch::LinkItem) => Some(Arguments::LinkItem {
+                item: Id::new(destination_is_immediate_value, destination & 0xffff),
+            }),
+            Command::Branch(Branch::LinkMark) => Some(Arguments::LinkMark {
+                mark: Id::new(destination_is_immediate_value, destination & 0xffff),
+            }),
+            Command::Set(Set::SetStream) => Some(Arguments::SetStream {
+                primary_audio: (destination & 0x80000000 != 0)
+                    .then(|| Id::new(destination_is_immediate_value, (destination >> 16) & 0xfff)),
+                pg_textst: (destination & 0x8000 != 0)
+                    .then(|| Id::new(destination_is_immediate_value, destination & 0xfff)),
+                pg_textst_displayed: destination & 0x4000 != 0,
+                interactive_graphics: (source & 0x80000000 != 0)
+                    .then(|| Id::new(source_is_immediate_value, (source >> 16) & 0xff)),
+                angle: (source & 0x8000 != 0)
+                    .then(|| Id::new(source_is_immediate_value, source & 0xff)),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bluray::NavigationCommand;
+
+    fn arguments(bytes: [u8; 12]) -> String {
+        NavigationCommand::from_bytes(&bytes)
+            .unwrap()
+            .arguments()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn button_page_decodes_flagged_fields() {
+        // Button 5, page 2 with out effects skipped, both immediate.
+        let bytes = [
+            0x51, 0xc0, 0x00, 0x03, 0x80, 0x00, 0x00, 0x05, 0xc0, 0x00, 0x00, 0x02,
+        ];
+        assert_eq!(
+            arguments(bytes),
+            "ButtonPage page=2 button=5 skip_out_effects"
+        );
+        // Without the flags, neither the button nor the page changes, whatever the IDs are.
+        let bytes = [
+            0x51, 0xc0, 0x00, 0x03, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x02,
+        ];
+        assert_eq!(arguments(bytes), "ButtonPage");
+        // The button from gpr7, and the page from gpr8.
+        let bytes = [
+            0x51, 0x00, 0x00, 0x03, 0x80, 0x00, 0x00, 0x07, 0x80, 0x00, 0x00, 0x08,
+        ];
+        assert_eq!(arguments(bytes), "ButtonPage page=gpr8 button=gpr7");
+    }
+
+    #[test]
+    fn enable_button_decodes_button() {
+        let bytes = [
+            0x31, 0x80, 0x00, 0x04, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(arguments(bytes), "EnableButton button=7");
+        let bytes = [
+            0x31, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(arguments(bytes), "EnableButton button=gpr3");
+    }
+
+    #[test]
+    fn nv_timer_decodes_movie_object_and_timeout() {
+        // Jump to movie object 3 after 60 seconds.
+        let bytes = [
+            0x51, 0xc0, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x3c,
+        ];
+        assert_eq!(arguments(bytes), "NvTimer movie_object=3 timeout=60s");
+        // The timeout from gpr5.
+        let bytes = [
+            0x51, 0x80, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x05,
+        ];
+        assert_eq!(arguments(bytes), "NvTimer movie_object=3 timeout=gpr5");
+    }
+
+    #[test]
+    fn set_stream_labels_unflagged_streams_as_kept() {
+        // Primary audio 2 with subtitles displayed, and angle 1; the others are kept.
+        let bytes = [
+            0x51, 0xc0, 0x00, 0x01, 0x80, 0x02, 0x40, 0x00, 0x00, 0x00, 0x80, 0x01,
+        ];
+        assert_eq!(
+            arguments(bytes),
+            "SetStream primary_audio=2 pg_textst=keep pg_textst_display=on ig=keep angle=1"
+        );
+        // Subtitles from gpr3, but hidden, and interactive graphics from gpr4. The primary audio
+        // ID is ignored without its flag.
+        let bytes = [
+            0x51, 0x00, 0x00, 0x01, 0x00, 0x05, 0x80, 0x03, 0x80, 0x04, 0x00, 0x00,
+        ];
+        assert_eq!(
+            arguments(bytes),
+            "SetStream primary_audio=keep pg_textst=gpr3 pg_textst_display=off ig=gpr4 angle=keep"
+        );
+    }
+
+    #[test]
+    fn link_targets_are_decoded() {
+        // LINK_ITEM to play item 4, and LINK_MARK to the mark in gpr9.
+        let bytes = [
+            0x22, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(arguments(bytes), "LinkItem item=4");
+        let bytes = [
+            0x22, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(arguments(bytes), "LinkMark mark=gpr9");
+    }
+}
@@ -0,0 +1,312 @@
+//! A textual assembly format for navigation commands, e.g. `MOV gpr1, psr20` or `JUMP_OBJECT 3`.
+//!
+//! Operands are written as `gprN` (or `rN`) for general-purpose registers, `psrN` for
+//! player-specific registers, plain decimal or `0x`-prefixed hex numbers for immediates, and
+//! `raw(0xN)` for non-immediate operands that aren't registers.
+
+use super::{
+    Branch, Command, Compare, NavigationCommand, NavigationCommandParseError, Operand,
+    OperandCount, RawCommand, Set, encode_command,
+};
+use thiserror::Error;
+
+const MNEMONICS: &[(&str, Command)] = &[
+    ("NOP", Command::Branch(Branch::Nop)),
+    ("GOTO", Command::Branch(Branch::GoTo)),
+    ("BREAK", Command::Branch(Branch::Break)),
+    ("JUMP_OBJECT", Command::Branch(Branch::JumpObject)),
+    ("JUMP_TITLE", Command::Branch(Branch::JumpTitle)),
+    ("CALL_OBJECT", Command::Branch(Branch::CallObject)),
+    ("CALL_TITLE", Command::Branch(Branch::CallTitle)),
+    ("RESUME", Command::Branch(Branch::Resume)),
+    ("PLAY_PL", Command::Branch(Branch::PlayList)),
+    ("PLAY_PI", Command::Branch(Branch::PlayItem)),
+    ("PLAY_PM", Command::Branch(Branch::PlayMark)),
+    ("TERMINATE", Command::Branch(Branch::Terminate)),
+    ("LINK_PI", Command::Branch(Branch::LinkItem)),
+    ("LINK_MK", Command::Branch(Branch::LinkMark)),
+    ("BC", Command::Compare(Compare::Bc)),
+    ("EQ", Command::Compare(Compare::Eq)),
+    ("NE", Command::Compare(Compare::Ne)),
+    ("GE", Command::Compare(Compare::Ge)),
+    ("GT", Command::Compare(Compare::Gt)),
+    ("LE", Command::Compare(Compare::Le)),
+    ("LT", Command::Compare(Compare::Lt)),
+    ("MOV", Command::Set(Set::Move)),
+    ("SWAP", Command::Set(Set::Swap)),
+    ("ADD", Command::Set(Set::Add)),
+    ("SUB", Command::Set(Set::Sub)),
+    ("MUL", Command::Set(Set::Mul)),
+    ("DIV", Command::Set(Set::Div)),
+    ("MOD", Command::Set(Set::Mod)),
+    ("RND", Command::Set(Set::Rnd)),
+    ("AND", Command::Set(Set::And)),
+    ("OR", Command::Set(Set::Or)),
+    ("XOR", Command::Set(Set::Xor)),
+    ("BITSET", Command::Set(Set::Bitset)),
+    ("BITCLR", Command::Set(Set::Bitclr)),
+    ("SHL", Command::Set(Set::ShiftLeft)),
+    ("SHR", Command::Set(Set::ShiftRight)),
+    ("SET_STREAM", Command::Set(Set::SetStream)),
+    ("SET_NV_TIMER", Command::Set(Set::SetNVTimer)),
+    ("BUTTON_PAGE", Command::Set(Set::ButtonPage)),
+    ("ENABLE_BUTTON", Command::Set(Set::EnableButton)),
+    ("DISABLE_BUTTON", Command::Set(Set::DisableButton)),
+    ("SET_SEC_STREAM", Command::Set(Set::SetSecondaryStream)),
+    ("POPUP_OFF", Command::Set(Set::PopupOff)),
+    ("STILL_ON", Command::Set(Set::StillOn)),
+    ("STILL_OFF", Command::Set(Set::StillOff)),
+];
+
+#[derive(Debug, Error)]
+pub enum AssembleError {
+    #[error("empty command")]
+    Empty,
+    #[error("unknown mnemonic {0:?}")]
+    UnknownMnemonic(String),
+    #[error("invalid operand {0:?}")]
+    InvalidOperand(String),
+    #[error("too many operands")]
+    TooManyOperands,
+    #[error("failed to encode command")]
+    EncodeFailed(#[source] NavigationCommandParseError),
+}
+
+impl Command {
+    /// The assembly mnemonic for this command. Reserved commands have no mnemonic that can be
+    /// assembled.
+    pub fn mnemonic(&self) -> &'static str {
+        if let Command::Reserved { .. } = self {
+            return "RESERVED";
+        }
+        MNEMONICS
+            .iter()
+            .find(|(_, command)| command == self)
+            .map(|(mnemonic, _)| *mnemonic)
+            .unwrap()
+    }
+}
+
+/// The alternate form, e.g. `{:#}`, also describes known PSRs, e.g. "psr20 (region)". Only the
+/// normal form can be parsed back.
+impl std::fmt::Display for Operand {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Immediate(value) => write!(fmt, "{value}")?,
+            Operand::Gpr(num) => write!(fmt, "gpr{num}")?,
+            Operand::Psr(num) => write!(fmt, "psr{num}")?,
+            Operand::Unknown(value) => write!(fmt, "raw({value:#x})")?,
+        }
+        match self.psr_role() {
+            Some(role) if fmt.alternate() => write!(fmt, " ({role})"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Operand {
+    /// Returns whether the operand is an immediate and its encoded 32-bit value.
+    pub(super) fn encode(&self) -> (bool, u32) {
+        match *self {
+            Operand::Immediate(value) => (true, value),
+            Operand::Gpr(num) => (false, num.into()),
+            Operand::Psr(num) => (false, 0x80000000 | u32::from(num)),
+            Operand::Unknown(value) => (false, value),
+        }
+    }
+}
+
+impl std::str::FromStr for Operand {
+    type Err = AssembleError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || AssembleError::InvalidOperand(s.to_string());
+        let lower = s.to_ascii_lowercase();
+        let parse_number = |s: &str| match s.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => s.parse(),
+        };
+        // Check raw( before the registers, since it also starts with r.
+        if let Some(value) = lower
+            .strip_prefix("raw(")
+            .and_then(|value| value.strip_suffix(')'))
+        {
+            Ok(Operand::Unknown(
+                parse_number(value).map_err(|_| invalid())?,
+            ))
+        } else if let Some(num) = lower.strip_prefix("psr") {
+            let num: u8 = num.parse().map_err(|_| invalid())?;
+            (num < 128).then_some(Operand::Psr(num)).ok_or_else(invalid)
+        } else if let Some(num) = lower.strip_prefix("gpr").or(lower.strip_prefix('r')) {
+            let num: u16 = num.parse().map_err(|_| invalid())?;
+            (num < 4096)
+                .then_some(Operand::Gpr(num))
+                .ok_or_else(invalid)
+        } else {
+            Ok(Operand::Immediate(
+                parse_number(&lower).map_err(|_| invalid())?,
+            ))
+        }
+    }
+}
+
+/// As with `Operand`, the alternate form describes known PSRs.
+impl std::fmt::Display for NavigationCommand {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "{}", self.command.mnemonic())?;
+        if let Command::Reserved {
+            group,
+            sub_group,
+            options,
+        } = self.command
+        {
+            write!(fmt, "({group}, {sub_group}, {options:#x})")?;
+        }
+        match (self.operand_count, fmt.alternate()) {
+            (OperandCount::None, _) => Ok(()),
+            (OperandCount::DestinationOnly, false) => write!(fmt, " {}", self.destination),
+            (OperandCount::DestinationOnly, true) => write!(fmt, " {:#}", self.destination),
+            (OperandCount::DestinationAndSource, false) => {
+                write!(fmt, " {}, {}", self.destination, self.source)
+            }
+            (OperandCount::DestinationAndSource, true) => {
+                write!(fmt, " {:#}, {:#}", self.destination, self.source)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for NavigationCommand {
+    type Err = AssembleError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (mnemonic, operands) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        if mnemonic.is_empty() {
+            return Err(AssembleError::Empty);
+        }
+        let command = MNEMONICS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(mnemonic))
+            .map(|(_, command)| *command)
+            .ok_or_else(|| AssembleError::UnknownMnemonic(mnemonic.to_string()))?;
+        let operands = operands
+            .split(',')
+            .map(str::trim)
+            .filter(|operand| !operand.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<Operand>, _>>()?;
+        let (operand_count, destination, source) = match operands[..] {
+            [] => (0, (false, 0), (false, 0)),
+            [destination] => (1, destination.encode(), (false, 0)),
+            [destination, source] => (2, destination.encode(), source.encode()),
+            _ => return Err(AssembleError::TooManyOperands),
+        };
+
+        let (command_group, command_sub_group, branch_option, compare_option, set_option) =
+            encode_command(command);
+        NavigationCommand::from_raw(RawCommand {
+            operand_count,
+            command_group,
+            command_sub_group,
+            destination_is_immediate_value: destination.0,
+            source_is_immediate_value: source.0,
+            branch_option,
+            compare_option,
+            set_option,
+            destination: destination.1,
+            source: source.1,
+            reserved: [0; 3],
+        })
+        .map_err(AssembleError::EncodeFailed)
+    }
+}
+
+impl NavigationCommand {
+    /// Renders the command in the textual convention of the lw/BluRay wiki, which the opcode
+    /// decoding is based on: the raw bytes as three big-endian 32-bit hex words, followed by the
+    /// wiki's name for the opcode (the same names as the `Branch`, `Compare`, and `Set` variants)
+    /// and the operands, with registers written as `GPRn`/`PSRn` and immediates in decimal.
+    pub fn to_wiki_string(self) -> String {
+        let words = self
+            .raw_bytes
+            .chunks_exact(4)
+            .map(|word| format!("{:08X}", u32::from_be_bytes(word.try_into().unwrap())))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let name = match self.command {
+            Command::Branch(branch) => format!("{branch:?}"),
+            Command::Compare(compare) => format!("{compare:?}"),
+            Command::Set(set) => format!("{set:?}"),
+            Command::Reserved {
+                group,
+                sub_group,
+                options,
+            } => format!("Reserved({group}, {sub_group}, {options})"),
+        };
+        let operand = |operand: &Operand| match operand {
+            Operand::Immediate(value) => format!("{value}"),
+            Operand::Gpr(num) => format!("GPR{num}"),
+            Operand::Psr(num) => format!("PSR{num}"),
+            Operand::Unknown(value) => format!("{value:#010X}"),
+        };
+        match self.operand_count {
+            OperandCount::None => format!("{words}  {name}"),
+            OperandCount::DestinationOnly => {
+                format!("{words}  {name} {}", operand(&self.destination))
+            }
+            OperandCount::DestinationAndSource => format!(
+                "{words}  {name} {}, {}",
+                operand(&self.destination),
+                operand(&self.source)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{NavigationCommand, Operand};
+
+    #[test]
+    fn operands_parse() {
+        assert!(matches!("gpr7".parse(), Ok(Operand::Gpr(7))));
+        assert!(matches!("r7".parse(), Ok(Operand::Gpr(7))));
+        assert!(matches!("PSR20".parse(), Ok(Operand::Psr(20))));
+        assert!(matches!("0x4a50".parse(), Ok(Operand::Immediate(0x4a50))));
+        assert!(matches!("raw(0x5)".parse(), Ok(Operand::Unknown(5))));
+        assert!("gpr4096".parse::<Operand>().is_err());
+        assert!("psr128".parse::<Operand>().is_err());
+    }
+
+    #[test]
+    fn assembly_round_trips() {
+        for assembly in [
+            "MOV gpr1, psr20",
+            "NE gpr1, 2",
+            "MOV gpr1, raw(0x5000)",
+            "GOTO 3",
+            "JUMP_OBJECT 1",
+            "NOP",
+        ] {
+            let command: NavigationCommand = assembly.parse().unwrap();
+            assert_eq!(command.to_string(), assembly);
+            assert_eq!(
+                NavigationCommand::from_bytes(&command.to_bytes())
+                    .unwrap()
+                    .to_string(),
+                assembly
+            );
+        }
+    }
+
+    #[test]
+    fn wiki_format_has_hex_words_and_named_operands() {
+        let bytes = [
+            0x48, 0x40, 0x02, 0x00, 0x80, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x02,
+        ];
+        let command = NavigationCommand::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            command.to_wiki_string(),
+            "48400200 80000014 00000002  Eq PSR20, 2"
+        );
+    }
+}
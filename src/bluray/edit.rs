@@ -0,0 +1,393 @@
+use super::{Branch, Command, MovieObjectFile, NavigationCommand, Operand, OperandCount};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EditError {
+    #[error("no movie object #{0}")]
+    NoSuchMovieObject(u16),
+    #[error("movie object #{0} has no navigation command #{1}")]
+    NoSuchNavigationCommand(u16, u16),
+    #[error("movie object #{0} has too many navigation commands")]
+    TooManyNavigationCommands(u16),
+}
+
+/// A change to a single navigation command.
+#[derive(Clone, Copy, Debug)]
+pub enum PatchAction {
+    /// Replace the command with a nop.
+    Nop,
+    /// Replace the command with an unconditional `GoTo` to this navigation command.
+    GoTo(u16),
+    /// Make the command read this PSR20 value as an immediate instead of PSR20.
+    SetRegion(u32),
+    /// Make the command read this PSR19 country code as an immediate instead of PSR19.
+    SetCountry([u8; 2]),
+    /// Remove the command, shifting `GoTo` targets after it to keep pointing at the same
+    /// commands.
+    Delete,
+}
+
+#[derive(Debug, Error)]
+pub enum PatchError {
+    #[error(transparent)]
+    OutOfRange(#[from] EditError),
+    #[error("movie object #{0} navigation command #{1} doesn't read PSR{2}")]
+    NoPsrRead(u16, u16, u8),
+}
+
+impl MovieObjectFile {
+    /// Inserts `command` so that it becomes navigation command `navigation_command_index` of the
+    /// movie object, which may be one past the last command to append. `GoTo` targets in the
+    /// movie object that point at or past the insertion are shifted to keep pointing at the same
+    /// commands.
+    pub fn insert_command(
+        &mut self,
+        movie_object_index: u16,
+        navigation_command_index: u16,
+        command: NavigationCommand,
+    ) -> Result<(), EditError> {
+        let commands = &mut self
+            .movie_objects
+            .movie_objects
+            .get_mut(usize::from(movie_object_index))
+            .ok_or(EditError::NoSuchMovieObject(movie_object_index))?
+            .navigation_commands;
+        if usize::from(navigation_command_index) > commands.len() {
+            return Err(EditError::NoSuchNavigationCommand(
+                movie_object_index,
+                navigation_command_index,
+            ));
+        }
+        if commands.len() >= usize::from(u16::MAX) {
+            return Err(EditError::TooManyNavigationCommands(movie_object_index));
+        }
+
+        for existing in commands.iter_mut() {
+            if let (Command::Branch(Branch::GoTo), Operand::Immediate(target)) =
+                (existing.command, existing.destination)
+                && target >= u32::from(navigation_command_index)
+            {
+                let mut raw = existing.raw();
+                raw.destination = target + 1;
+                *existing = NavigationCommand::from_raw(raw).unwrap();
+            }
+        }
+        commands.insert(usize::from(navigation_command_index), command);
+        self.resize(12);
+        Ok(())
+    }
+
+    /// Clears the reserved header bytes, the reserved bytes before the movie objects, and the
+    /// reserved bits of every navigation command. Returns the number of bytes that changed.
+    pub fn zero_reserved(&mut self) -> usize {
+        let mut changed = self.header[12..].iter().filter(|&&byte| byte != 0).count()
+            + self
+                .movie_objects
+                .reserved
+                .iter()
+                .filter(|&&byte| byte != 0)
+                .count();
+        self.header[12..].fill(0);
+        self.movie_objects.reserved = [0; 4];
+        for command in self
+            .movie_objects
+            .movie_objects
+            .iter_mut()
+            .flat_map(|object| object.navigation_commands.iter_mut())
+        {
+            let mut raw = command.raw();
+            changed += raw.reserved.iter().filter(|&&bits| bits != 0).count();
+            raw.reserved = [0; 3];
+            *command = NavigationCommand::from_raw(raw).unwrap();
+        }
+        changed
+    }
+
+    /// Clears the immediate flag of every operand that a navigation command's operand count says
+    /// is unused. Returns the number of navigation commands that changed.
+    pub fn normalize_immediate_flags(&mut self) -> usize {
+        let mut changed = 0;
+        for command in self
+            .movie_objects
+            .movie_objects
+            .iter_mut()
+            .flat_map(|object| object.navigation_commands.iter_mut())
+        {
+            let mut raw = command.raw();
+            let (destination_used, source_used) = match command.operand_count {
+                OperandCount::None => (false, false),
+                OperandCount::DestinationOnly => (true, false),
+                OperandCount::DestinationAndSource => (true, true),
+            };
+            if (raw.destination_is_immediate_value && !destination_used)
+                || (raw.source_is_immediate_value && !source_used)
+            {
+                raw.destination_is_immediate_value &= destination_used;
+                raw.source_is_immediate_value &= source_used;
+                *command = NavigationCommand::from_raw(raw).unwrap();
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// Deletes every nop that directly follows another nop, so each run of nops shrinks to one.
+    /// GoTo targets are shifted like for [`PatchAction::Delete`]. Returns the number of nops
+    /// deleted.
+    pub fn collapse_nops(&mut self) -> usize {
+        let is_nop = |command: &NavigationCommand| command.command == Command::Branch(Branch::Nop);
+        let mut redundant = vec![];
+        for (i, object) in (0..).zip(self.movie_objects.movie_objects.iter()) {
+            for (j, pair) in (1..).zip(object.navigation_commands.windows(2)) {
+                if is_nop(&pair[0]) && is_nop(&pair[1]) {
+                    redundant.push((i, j));
+                }
+            }
+        }
+        // Delete from the back so earlier deletions don't shift the locations of later ones.
+        for &(i, j) in redundant.iter().rev() {
+            self.apply_patch(i, j, PatchAction::Delete).unwrap();
+        }
+        redundant.len()
+    }
+
+    /// Rewrites the file into a canonical form that keeps only what players act on: reserved
+    /// bytes and bits, reserved movie object flags, and immediate flags of unused operands are
+    /// cleared, and every nop becomes the canonical all-zero nop.
+    pub fn canonicalize(&mut self) {
+        self.zero_reserved();
+        self.normalize_immediate_flags();
+        for object in &mut self.movie_objects.movie_objects {
+            object.header &= !object.undocumented_flags();
+            for command in &mut object.navigation_commands {
+                if let Command::Branch(Branch::Nop) = command.command {
+                    *command = NavigationCommand::nop();
+                }
+            }
+        }
+    }
+
+    /// Applies `action` to navigation command `navigation_command_index` of the movie object.
+    pub fn apply_patch(
+        &mut self,
+        movie_object_index: u16,
+        navigation_command_index: u16,
+        action: PatchAction,
+    ) -> Result<(), PatchError> {
+        let commands = &mut self
+            .movie_objects
+            .movie_objects
+            .get_mut(usize::from(movie_object_index))
+            .ok_or(EditError::NoSuchMovieObject(movie_object_index))?
+            .navigation_commands;
+        let command = commands
+            .get_mut(usize::from(navigation_command_index))
+            .ok_or(EditError::NoSuchNavigationCommand(
+                movie_object_index,
+                navigation_command_index,
+            ))?;
+        let (psr, value) = match action {
+            PatchAction::Nop => {
+                *command = NavigationCommand::nop();
+                return Ok(());
+            }
+            PatchAction::GoTo(target) => {
+                *command = NavigationCommand::goto(target);
+                return Ok(());
+            }
+            PatchAction::Delete => {
+                commands.remove(usize::from(navigation_command_index));
+                for existing in commands.iter_mut() {
+                    if let (Command::Branch(Branch::GoTo), Operand::Immediate(target)) =
+                        (existing.command, existing.destination)
+                        && target > u32::from(navigation_command_index)
+                    {
+                        let mut raw = existing.raw();
+                        raw.destination = target - 1;
+                        *existing = NavigationCommand::from_raw(raw).unwrap();
+                    }
+                }
+                self.resize(-12);
+                return Ok(());
+            }
+            PatchAction::SetRegion(region) => (20, region),
+            PatchAction::SetCountry(country) => (19, u32::from(u16::from_be_bytes(country))),
+        };
+        // Both PSR19 (country) and PSR20 (region) are read-only, so they can only be read: as
+        // the source of any command, or as either side of a compare.
+        let mut raw = command.raw();
+        let mut rewritten = false;
+        if let OperandCount::DestinationAndSource = command.operand_count
+            && matches!(command.source, Operand::Psr(num) if num == psr)
+        {
+            raw.source_is_immediate_value = true;
+            raw.source = value;
+            rewritten = true;
+        }
+        if let Command::Compare(_) = command.command
+            && !matches!(command.operand_count, OperandCount::None)
+            && matches!(command.destination, Operand::Psr(num) if num == psr)
+        {
+            raw.destination_is_immediate_value = true;
+            raw.destination = value;
+            rewritten = true;
+        }
+        if !rewritten {
+            return Err(PatchError::NoPsrRead(
+                movie_object_index,
+                navigation_command_index,
+                psr,
+            ));
+        }
+        *command = NavigationCommand::from_raw(raw).unwrap();
+        Ok(())
+    }
+
+    /// Adjusts the recorded movie objects length and extension data start address after the
+    /// movie objects grew or shrank by `delta` bytes.
+    fn resize(&mut self, delta: i64) {
+        self.movie_objects.byte_len = u32::try_from(i64::from(self.movie_objects.byte_len) + delta)
+            .expect("movie objects length out of range");
+        let extension_data_start = u32::from_be_bytes(self.header[8..12].try_into().unwrap());
+        // A zero start address means there is no extension data.
+        if extension_data_start != 0 {
+            let extension_data_start = u32::try_from(i64::from(extension_data_start) + delta)
+                .expect("extension data start address out of range");
+            self.header[8..12].copy_from_slice(&extension_data_start.to_be_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EditError, PatchAction, PatchError};
+    use crate::bluray::testing::{assembly, file};
+    use crate::bluray::{MovieObjectFile, Region, testing};
+
+    #[test]
+    fn region_free_writes_zero() {
+        let mut file = file(&[&["MOV gpr1, psr20", "EQ psr20, 2"]]);
+        let action = PatchAction::SetRegion(Region::Free.to_psr_value());
+        file.apply_patch(0, 0, action).unwrap();
+        file.apply_patch(0, 1, action).unwrap();
+        assert_eq!(assembly(&file, 0), ["MOV gpr1, 0", "EQ 0, 2"]);
+        let bytes = file.serialize();
+        // The immediate flags are set, and the operands that were PSR20 are zero.
+        assert_eq!(bytes[54..66], [0x50, 0x40, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0]);
+        assert_eq!(bytes[66..78], [0x48, 0xc0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn insert_shifts_goto_targets_at_or_after_it() {
+        let mut file = file(&[&["GOTO 0", "GOTO 1", "GOTO 2", "GOTO 3", "NOP"]]);
+        file.insert_command(0, 2, "MOV gpr1, 2".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            assembly(&file, 0),
+            ["GOTO 0", "GOTO 1", "MOV gpr1, 2", "GOTO 3", "GOTO 4", "NOP"]
+        );
+        // The count and length fields follow the new command.
+        let reparsed = MovieObjectFile::from_bytes(&file.serialize()).unwrap();
+        assert_eq!(assembly(&reparsed, 0), assembly(&file, 0));
+    }
+
+    #[test]
+    fn each_patch_action_changes_its_command() {
+        let mut file = file(&[&[
+            "MOV gpr1, psr20",
+            "EQ psr19, 0x4a50",
+            "JUMP_OBJECT 1",
+            "PLAY_PL 1",
+            "GOTO 5",
+            "TERMINATE",
+        ]]);
+        file.apply_patch(0, 0, PatchAction::SetRegion(2)).unwrap();
+        file.apply_patch(0, 1, PatchAction::SetCountry(*b"US"))
+            .unwrap();
+        file.apply_patch(0, 2, PatchAction::GoTo(4)).unwrap();
+        file.apply_patch(0, 3, PatchAction::Delete).unwrap();
+        // 21843 is 0x5553, "US", and the GoTos past the deleted PLAY_PL follow their targets.
+        assert_eq!(
+            assembly(&file, 0),
+            [
+                "MOV gpr1, 2",
+                "EQ 21843, 19024",
+                "GOTO 3",
+                "GOTO 4",
+                "TERMINATE"
+            ]
+        );
+        file.apply_patch(0, 4, PatchAction::Nop).unwrap();
+        assert_eq!(assembly(&file, 0)[4], "NOP");
+    }
+
+    #[test]
+    fn patch_errors_name_the_command() {
+        let mut file = file(&[&["MOV gpr1, psr20", "NOP"]]);
+        let err = file.apply_patch(1, 0, PatchAction::Nop).unwrap_err();
+        assert!(matches!(
+            err,
+            PatchError::OutOfRange(EditError::NoSuchMovieObject(1))
+        ));
+        let err = file.apply_patch(0, 2, PatchAction::Nop).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "movie object #0 has no navigation command #2"
+        );
+        // The region is read, not the country.
+        let err = file
+            .apply_patch(0, 0, PatchAction::SetCountry(*b"US"))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "movie object #0 navigation command #0 doesn't read PSR19"
+        );
+        let err = file
+            .apply_patch(0, 1, PatchAction::SetRegion(2))
+            .unwrap_err();
+        assert!(matches!(err, PatchError::NoPsrRead(0, 1, 20)));
+        // Nothing changed.
+        assert_eq!(assembly(&file, 0), ["MOV gpr1, psr20", "NOP"]);
+    }
+
+    #[test]
+    fn immediate_flags_follow_operand_count() {
+        let mut bytes = testing::bytes(&[&["NOP", "JUMP_OBJECT 1", "MOV gpr1, 2"]]);
+        // Flag the unused destination of the NOP and the unused source of the JUMP_OBJECT.
+        bytes[55] |= 0x80;
+        bytes[67] |= 0x40;
+        let mut file = MovieObjectFile::from_bytes(&bytes).unwrap();
+        assert_eq!(file.warnings().len(), 2);
+        assert_eq!(file.normalize_immediate_flags(), 2);
+        assert!(file.warnings().is_empty());
+        let normalized = file.serialize();
+        assert_eq!(
+            normalized,
+            testing::bytes(&[&["NOP", "JUMP_OBJECT 1", "MOV gpr1, 2"]])
+        );
+        assert_eq!(file.normalize_immediate_flags(), 0);
+    }
+
+    #[test]
+    fn nop_runs_collapse_to_one() {
+        let mut file = file(&[
+            &[
+                "GOTO 6",
+                "NOP",
+                "NOP",
+                "NOP",
+                "PLAY_PL 1",
+                "GOTO 3",
+                "TERMINATE",
+            ],
+            &["NOP", "PLAY_PL 2", "NOP"],
+        ]);
+        assert_eq!(file.collapse_nops(), 2);
+        // A GoTo into the deleted part of the run now goes where running through it would.
+        assert_eq!(
+            assembly(&file, 0),
+            ["GOTO 4", "NOP", "PLAY_PL 1", "GOTO 2", "TERMINATE"]
+        );
+        assert_eq!(assembly(&file, 1), ["NOP", "PLAY_PL 2", "NOP"]);
+    }
+}
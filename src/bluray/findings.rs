@@ -0,0 +1,382 @@
+use super::{Command, MovieObjectFile, NavigationCommand, Operand, OperandCount, Set};
+use std::collections::{HashMap, HashSet};
+
+/// How a navigation command uses PSR19 (country) or PSR20 (region).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RegionFindingKind {
+    /// The PSR is read directly, e.g. copied into a GPR or compared against a value.
+    DirectRead,
+    /// A GPR holding a value derived from the PSR is compared.
+    IndirectViaGpr(u16),
+    /// The PSR is written to. Both PSRs are read-only, so this is unexpected.
+    WriteAnomaly,
+    /// Neither side is known to come from PSR19, but the other side is an immediate that looks
+    /// like a country code. Only reported by [`MovieObjectFile::ascii_country_compares`].
+    AsciiCountryCompare,
+}
+
+/// A navigation command that uses PSR19 (country) or PSR20 (region).
+#[derive(Clone, Copy, Debug)]
+pub struct RegionFinding {
+    pub movie_object_index: u16,
+    pub navigation_command_index: u16,
+    /// 19 for country, 20 for region.
+    pub psr: u8,
+    pub kind: RegionFindingKind,
+    /// The immediate that the PSR, or the GPR derived from it, is compared against, if known.
+    pub compared: Option<u32>,
+}
+
+fn region_psr(operand: Operand) -> Option<u8> {
+    match operand {
+        Operand::Psr(num @ (19 | 20)) => Some(num),
+        _ => None,
+    }
+}
+
+/// Why a navigation command was or wasn't reported by [`MovieObjectFile::region_checks`].
+#[derive(Clone, Debug)]
+pub struct DetectionTrace {
+    pub movie_object_index: u16,
+    pub navigation_command_index: u16,
+    pub flagged: bool,
+    /// What detection saw in the command, e.g. "source is PSR20", or why it skipped it.
+    pub reason: String,
+}
+
+type Found = (u8, RegionFindingKind, Option<u32>);
+
+/// Checks a single navigation command for uses of PSR19 or PSR20, tracking which GPRs hold a
+/// value derived from them in `derived`. Returns a description of each decision that was made.
+fn detect_command(
+    command: &NavigationCommand,
+    derived: &mut HashMap<u16, u8>,
+    found: &mut Vec<Found>,
+) -> Vec<String> {
+    // Without operands, a command can't read or write PSR19 or PSR20, nor clobber a derived
+    // GPR.
+    if let OperandCount::None = command.operand_count {
+        return vec!["no operands".to_string()];
+    }
+    let mut reasons = vec![];
+    if let OperandCount::DestinationOnly = command.operand_count {
+        // Single-operand set commands could still clobber a PSR or a derived GPR.
+        if let Command::Set(_) = command.command {
+            if let Some(psr) = region_psr(command.destination) {
+                found.push((psr, RegionFindingKind::WriteAnomaly, None));
+                reasons.push(format!("destination is PSR{psr}, which is read-only"));
+            }
+            if let Operand::Gpr(gpr) = command.destination
+                && let Some(psr) = derived.remove(&gpr)
+            {
+                reasons.push(format!("overwrites gpr{gpr}, which held PSR{psr}"));
+            }
+        }
+        if reasons.is_empty() {
+            reasons.push("single operand, which isn't PSR19 or PSR20".to_string());
+        }
+        return reasons;
+    }
+
+    match command.command {
+        Command::Compare(_) => {
+            let sides = [
+                ("destination", command.destination, command.source),
+                ("source", command.source, command.destination),
+            ];
+            for (side, operand, other) in sides {
+                if let Some(psr) = region_psr(operand) {
+                    found.push((psr, RegionFindingKind::DirectRead, other.as_u32()));
+                    reasons.push(format!("compares PSR{psr} as the {side}"));
+                } else if let Operand::Gpr(gpr) = operand
+                    && let Some(&psr) = derived.get(&gpr)
+                {
+                    found.push((psr, RegionFindingKind::IndirectViaGpr(gpr), other.as_u32()));
+                    reasons.push(format!(
+                        "compares gpr{gpr}, which holds PSR{psr}, as the {side}"
+                    ));
+                }
+            }
+            if reasons.is_empty() {
+                reasons.push("compare without PSR19, PSR20, or a GPR loaded from them".to_string());
+            }
+        }
+        Command::Set(set) => {
+            if let Some(psr) = region_psr(command.source) {
+                found.push((psr, RegionFindingKind::DirectRead, None));
+                reasons.push(format!("source is PSR{psr}"));
+            }
+            if let Some(psr) = region_psr(command.destination) {
+                found.push((psr, RegionFindingKind::WriteAnomaly, None));
+                reasons.push(format!("destination is PSR{psr}, which is read-only"));
+            }
+            match (set, command.destination, command.source) {
+                (Set::Move, Operand::Gpr(gpr), source) => match region_psr(source) {
+                    Some(psr) => {
+                        derived.insert(gpr, psr);
+                        reasons.push(format!("gpr{gpr} now holds PSR{psr}"));
+                    }
+                    None => {
+                        if let Some(psr) = derived.remove(&gpr) {
+                            reasons.push(format!("overwrites gpr{gpr}, which held PSR{psr}"));
+                        }
+                    }
+                },
+                (Set::Swap, Operand::Gpr(first), Operand::Gpr(second)) => {
+                    let first_psr = derived.remove(&first);
+                    let second_psr = derived.remove(&second);
+                    if let Some(psr) = first_psr {
+                        derived.insert(second, psr);
+                        reasons.push(format!("gpr{second} now holds PSR{psr}"));
+                    }
+                    if let Some(psr) = second_psr {
+                        derived.insert(first, psr);
+                        reasons.push(format!("gpr{first} now holds PSR{psr}"));
+                    }
+                }
+                (Set::Rnd, Operand::Gpr(gpr), _) => {
+                    if let Some(psr) = derived.remove(&gpr) {
+                        reasons.push(format!("overwrites gpr{gpr}, which held PSR{psr}"));
+                    }
+                }
+                // Other arithmetic, e.g. masking with AND, keeps the value derived.
+                _ => {}
+            }
+            if reasons.is_empty() {
+                reasons.push("no PSR19 or PSR20 operand".to_string());
+            }
+        }
+        Command::Branch(_) => {
+            if let Some(psr) = region_psr(command.source) {
+                found.push((psr, RegionFindingKind::DirectRead, None));
+                reasons.push(format!("source is PSR{psr}"));
+            } else {
+                reasons.push("branch without a PSR19 or PSR20 source".to_string());
+            }
+        }
+        // Players ignore reserved commands, so they can't read either PSR.
+        Command::Reserved { .. } => reasons.push("reserved command".to_string()),
+    }
+    reasons
+}
+
+impl MovieObjectFile {
+    /// Finds all navigation commands that use PSR19 (country) or PSR20 (region), either directly
+    /// or through a GPR that was loaded from one of them earlier in the same movie object.
+    pub fn region_checks(&self) -> Vec<RegionFinding> {
+        self.detect(|_| {})
+    }
+
+    /// Explains, for every navigation command, why [`Self::region_checks`] did or didn't report
+    /// it.
+    pub fn trace_detection(&self) -> Vec<DetectionTrace> {
+        let mut traces = vec![];
+        self.detect(|trace| traces.push(trace));
+        traces
+    }
+
+    fn detect(&self, mut trace: impl FnMut(DetectionTrace)) -> Vec<RegionFinding> {
+        let mut findings = vec![];
+        for (i, object) in (0..).zip(self.movie_objects.movie_objects.iter()) {
+            // GPRs currently holding a value derived from PSR19 or PSR20.
+            let mut derived: HashMap<u16, u8> = HashMap::new();
+            for (j, command) in (0..).zip(object.navigation_commands.iter()) {
+                let mut found = vec![];
+                let reasons = detect_command(command, &mut derived, &mut found);
+                trace(DetectionTrace {
+                    movie_object_index: i,
+                    navigation_command_index: j,
+                    flagged: !found.is_empty(),
+                    reason: reasons.join("; "),
+                });
+                findings.extend(
+                    found
+                        .into_iter()
+                        .map(|(psr, kind, compared)| RegionFinding {
+                            movie_object_index: i,
+                            navigation_command_index: j,
+                            psr,
+                            kind,
+                            compared,
+                        }),
+                );
+            }
+        }
+        findings
+    }
+
+    /// Finds compares against an immediate whose low two bytes are uppercase ASCII letters and
+    /// whose high two bytes are zero, e.g. `EQ gpr7, "JP"`, that [`Self::region_checks`] didn't
+    /// already attribute to PSR19. This catches country checks where the GPR was loaded in an
+    /// unusual order or in another movie object, at the cost of the occasional false positive.
+    pub fn ascii_country_compares(&self) -> Vec<RegionFinding> {
+        let known: HashSet<(u16, u16)> = self
+            .region_checks()
+            .iter()
+            .map(|finding| (finding.movie_object_index, finding.navigation_command_index))
+            .collect();
+        let mut findings = vec![];
+        for (i, object) in (0..).zip(self.movie_objects.movie_objects.iter()) {
+            for (j, command) in (0..).zip(object.navigation_commands.iter()) {
+                if !matches!(command.command, Command::Compare(_))
+                    || !matches!(command.operand_count, OperandCount::DestinationAndSource)
+                    || known.contains(&(i, j))
+                {
+                    continue;
+                }
+                let compared =
+                    [command.source, command.destination]
+                        .into_iter()
+                        .find_map(|operand| {
+                            operand.as_ascii_pair()?;
+                            operand.as_u32().filter(|&value| value <= 0xffff)
+                        });
+                if compared.is_some() {
+                    findings.push(RegionFinding {
+                        movie_object_index: i,
+                        navigation_command_index: j,
+                        psr: 19,
+                        kind: RegionFindingKind::AsciiCountryCompare,
+                        compared,
+                    });
+                }
+            }
+        }
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RegionFinding, RegionFindingKind};
+    use crate::bluray::{MovieObjectFile, testing};
+
+    fn summarize(
+        findings: &[RegionFinding],
+    ) -> Vec<(u16, u16, u8, RegionFindingKind, Option<u32>)> {
+        findings
+            .iter()
+            .map(|finding| {
+                (
+                    finding.movie_object_index,
+                    finding.navigation_command_index,
+                    finding.psr,
+                    finding.kind,
+                    finding.compared,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn direct_reads_are_found() {
+        let file = testing::file(&[&["EQ psr20, 2", "NOP", "GT 0x4a50, psr19", "NOP"]]);
+        assert_eq!(
+            summarize(&file.region_checks()),
+            [
+                (0, 0, 20, RegionFindingKind::DirectRead, Some(2)),
+                (0, 2, 19, RegionFindingKind::DirectRead, Some(0x4a50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn compares_of_loaded_gprs_are_found() {
+        let file = testing::file(&[
+            &[
+                "MOV gpr1, psr20",
+                "AND gpr1, 3",
+                "NE gpr1, 2",
+                "JUMP_OBJECT 1",
+                // Overwriting the GPR ends the tracking.
+                "MOV gpr1, 0",
+                "NE gpr1, 2",
+            ],
+            // Tracking doesn't carry over into other movie objects.
+            &["NE gpr1, 2"],
+        ]);
+        assert_eq!(
+            summarize(&file.region_checks()),
+            [
+                (0, 0, 20, RegionFindingKind::DirectRead, None),
+                (0, 2, 20, RegionFindingKind::IndirectViaGpr(1), Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_are_anomalies() {
+        let file = testing::file(&[&["MOV psr19, 0x4a50", "ADD psr20, 1"]]);
+        assert_eq!(
+            summarize(&file.region_checks()),
+            [
+                (0, 0, 19, RegionFindingKind::WriteAnomaly, None),
+                (0, 1, 20, RegionFindingKind::WriteAnomaly, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn ascii_country_compares_are_found_separately() {
+        let file = testing::file(&[&[
+            "MOV gpr1, psr19",
+            "EQ gpr1, 0x4a50",
+            "NOP",
+            "EQ gpr7, 0x5553",
+            "NOP",
+            // Not a country code: lowercase, and too large.
+            "EQ gpr7, 0x7573",
+            "EQ gpr7, 0x10005553",
+        ]]);
+        assert_eq!(
+            summarize(&file.ascii_country_compares()),
+            [(
+                0,
+                3,
+                19,
+                RegionFindingKind::AsciiCountryCompare,
+                Some(0x5553)
+            )]
+        );
+        assert!(
+            file.region_checks()
+                .iter()
+                .all(|finding| finding.kind != RegionFindingKind::AsciiCountryCompare)
+        );
+    }
+
+    #[test]
+    fn commands_without_operands_are_never_flagged() {
+        let mut bytes = testing::bytes(&[&["EQ psr20, 2", "MOV gpr1, psr20", "EQ gpr1, 2"]]);
+        // Clear the operand count of the first two commands, at bytes 54 and 66, leaving their
+        // operand bytes as they were.
+        bytes[54] &= 0x1f;
+        bytes[66] &= 0x1f;
+        let file = MovieObjectFile::from_bytes(&bytes).unwrap();
+        assert!(file.region_checks().is_empty());
+    }
+
+    #[test]
+    fn ascii_country_compares_are_found_in_either_order() {
+        let file = testing::file(&[&["GE 0x4742, gpr3", "NOP", "NE gpr4, 0x4b52"]]);
+        assert_eq!(
+            summarize(&file.ascii_country_compares()),
+            [
+                (
+                    0,
+                    0,
+                    19,
+                    RegionFindingKind::AsciiCountryCompare,
+                    Some(0x4742)
+                ),
+                (
+                    0,
+                    2,
+                    19,
+                    RegionFindingKind::AsciiCountryCompare,
+                    Some(0x4b52)
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use super::MovieObjectFile;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+const INDEX_TYPE: &[u8; 4] = b"INDX";
+/// The size of the first playback, top menu, and title entries.
+const ENTRY_LEN: usize = 12;
+
+#[derive(Debug, Error)]
+pub enum IndexError {
+    #[error("IO error for {0}")]
+    IoError(PathBuf, #[source] std::io::Error),
+    #[error("invalid index.bdmv: header too short")]
+    TruncatedHeader,
+    #[error("invalid index.bdmv header: {0:#04x?}")]
+    BadMagicBytes([u8; 4]),
+    #[error("invalid index.bdmv: indexes at {0:#x} are truncated")]
+    IndexesTruncated(usize),
+}
+
+/// What the first playback, the top menu, or a title starts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndexObject {
+    /// An HDMV movie object, by its index in MovieObject.bdmv.
+    MovieObject(u16),
+    /// A BD-J object, which runs Java code rather than navigation commands.
+    BdJ,
+    /// Any other object type, e.g. 0 for a top menu that a disc doesn't have.
+    Other(u8),
+}
+
+impl IndexObject {
+    fn from_entry(entry: &[u8; ENTRY_LEN]) -> IndexObject {
+        // The object type is in the top two bits. For titles, the access type follows, and the
+        // rest of the first four bytes are reserved either way. An HDMV object then has its
+        // playback type and reserved bits, and the movie object index in bytes 6..8.
+        match entry[0] >> 6 {
+            1 => IndexObject::MovieObject(u16::from_be_bytes([entry[6], entry[7]])),
+            2 => IndexObject::BdJ,
+            other => IndexObject::Other(other),
+        }
+    }
+}
+
+/// Where playback of a disc can start, as listed in index.bdmv.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum EntryPoint {
+    FirstPlayback,
+    TopMenu,
+    /// A title, numbered from 1 as players show them.
+    Title(u16),
+}
+
+impl fmt::Display for EntryPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryPoint::FirstPlayback => write!(f, "First Playback"),
+            EntryPoint::TopMenu => write!(f, "Top Menu"),
+            EntryPoint::Title(number) => write!(f, "Title {number}"),
+        }
+    }
+}
+
+/// The parts of index.bdmv that map titles to movie objects. The AppInfoBDMV block and the
+/// extension data aren't kept.
+#[derive(Debug)]
+pub struct IndexFile {
+    pub first_playback: IndexObject,
+    pub top_menu: IndexObject,
+    pub titles: Vec<IndexObject>,
+}
+
+impl IndexFile {
+    pub fn open(path: &Path) -> Result<Self, IndexError> {
+        let contents = std::fs::read(path).map_err(|e| IndexError::IoError(path.to_owned(), e))?;
+        Self::from_bytes(&contents)
+    }
+
+    pub fn from_bytes(contents: &[u8]) -> Result<Self, IndexError> {
+        // Bytes 0..4 are the type indicator ("INDX"), 4..8 the version, 8..12 the indexes start
+        // address, and 12..16 the extension data start address.
+        let header = contents
+            .first_chunk::<16>()
+            .ok_or(IndexError::TruncatedHeader)?;
+        let magic_bytes = header.first_chunk::<4>().unwrap();
+        if magic_bytes != INDEX_TYPE {
+            return Err(IndexError::BadMagicBytes(*magic_bytes));
+        }
+        let start = usize::try_from(u32::from_be_bytes(header[8..12].try_into().unwrap())).unwrap();
+        // Skip the length of the indexes.
+        let indexes = contents
+            .get(start + 4..)
+            .ok_or(IndexError::IndexesTruncated(start))?;
+        let (first_playback, indexes) = indexes
+            .split_first_chunk::<ENTRY_LEN>()
+            .ok_or(IndexError::IndexesTruncated(start))?;
+        let (top_menu, indexes) = indexes
+            .split_first_chunk::<ENTRY_LEN>()
+            .ok_or(IndexError::IndexesTruncated(start))?;
+        let (count, mut indexes) = indexes
+            .split_first_chunk::<2>()
+            .ok_or(IndexError::IndexesTruncated(start))?;
+        let mut titles = vec![];
+        for _ in 0..u16::from_be_bytes(*count) {
+            let (title, remainder) = indexes
+                .split_first_chunk::<ENTRY_LEN>()
+                .ok_or(IndexError::IndexesTruncated(start))?;
+            titles.push(IndexObject::from_entry(title));
+            indexes = remainder;
+        }
+        Ok(IndexFile {
+            first_playback: IndexObject::from_entry(first_playback),
+            top_menu: IndexObject::from_entry(top_menu),
+            titles,
+        })
+    }
+
+    /// Each entry point and the movie object it starts at, skipping those that don't start at
+    /// a movie object.
+    pub fn entry_points(&self) -> Vec<(EntryPoint, u16)> {
+        [
+            (EntryPoint::FirstPlayback, self.first_playback),
+            (EntryPoint::TopMenu, self.top_menu),
+        ]
+        .into_iter()
+        .chain(
+            (1..)
+                .zip(&self.titles)
+                .map(|(n, &title)| (EntryPoint::Title(n), title)),
+        )
+        .filter_map(|(entry_point, object)| match object {
+            IndexObject::MovieObject(index) => Some((entry_point, index)),
+            _ => None,
+        })
+        .collect()
+    }
+
+    /// The entry points whose movie object reaches the given movie object, following
+    /// `JumpObject` and `CallObject`.
+    pub fn entry_points_reaching(
+        &self,
+        file: &MovieObjectFile,
+        movie_object_index: u16,
+    ) -> Vec<EntryPoint> {
+        self.entry_points()
+            .into_iter()
+            .filter(|&(_, start)| file.reachable_objects(start).contains(&movie_object_index))
+            .map(|(entry_point, _)| entry_point)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EntryPoint, IndexError, IndexFile, IndexObject};
+    use crate::bluray::testing;
+
+    #[test]
+    fn titles_map_to_movie_objects() {
+        let mut bytes = testing::index_bytes(0, 1, &[2, 3]);
+        // Make the second title a BD-J title. The indexes start at 78 with their length, then the
+        // first playback and top menu entries, the count of titles, and the first title.
+        bytes[78 + 4 + 12 + 12 + 2 + 12] = 0x80;
+        let index = IndexFile::from_bytes(&bytes).unwrap();
+        assert_eq!(index.first_playback, IndexObject::MovieObject(0));
+        assert_eq!(index.top_menu, IndexObject::MovieObject(1));
+        assert_eq!(
+            index.titles,
+            [IndexObject::MovieObject(2), IndexObject::BdJ]
+        );
+        assert_eq!(
+            index.entry_points(),
+            [
+                (EntryPoint::FirstPlayback, 0),
+                (EntryPoint::TopMenu, 1),
+                (EntryPoint::Title(1), 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_indexes_are_rejected() {
+        let bytes = testing::index_bytes(0, 0, &[0, 0]);
+        let err = IndexFile::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(matches!(err, IndexError::IndexesTruncated(78)));
+        let err = IndexFile::from_bytes(b"MOBJ0200").unwrap_err();
+        assert!(matches!(err, IndexError::TruncatedHeader));
+    }
+
+    #[test]
+    fn entry_points_reach_through_jumps_and_calls() {
+        let file = testing::file(&[
+            &["CALL_OBJECT 2", "JUMP_OBJECT 1"],
+            &["JUMP_TITLE 1"],
+            &["EQ psr20, 2", "JUMP_OBJECT 3"],
+            &["NOP"],
+        ]);
+        let index = IndexFile::from_bytes(&testing::index_bytes(1, 1, &[0, 3])).unwrap();
+        assert_eq!(
+            index.entry_points_reaching(&file, 2),
+            [EntryPoint::Title(1)]
+        );
+        assert_eq!(
+            index.entry_points_reaching(&file, 3),
+            [EntryPoint::Title(1), EntryPoint::Title(2)]
+        );
+        assert_eq!(
+            index.entry_points_reaching(&file, 1),
+            [
+                EntryPoint::FirstPlayback,
+                EntryPoint::TopMenu,
+                EntryPoint::Title(1)
+            ]
+        );
+    }
+}
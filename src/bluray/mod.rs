@@ -1,10 +1,42 @@
+mod analysis;
+mod arguments;
+mod asm;
+mod countries;
+mod edit;
+mod findings;
+mod index;
+mod model;
+mod profile;
+mod raw;
+#[cfg(test)]
+pub(crate) mod testing;
+mod validation;
+mod warnings;
+
+pub use analysis::{Access, ComputedGate};
+pub use asm::AssembleError;
+pub use countries::is_country_code;
+pub use edit::{EditError, PatchAction, PatchError};
+pub use findings::{DetectionTrace, RegionFinding, RegionFindingKind};
+pub use index::{IndexError, IndexFile};
+pub use model::{DiscModel, MovieObjectModel};
+pub use profile::{ProfileCheck, describe_profile};
+pub use raw::RawCommand;
+pub use warnings::Warning;
+
 use clap::ValueEnum;
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
-const MOVIE_OBJECT_HEADER: &[u8] = b"MOBJ0200";
+const MOVIE_OBJECT_TYPE: &[u8; 4] = b"MOBJ";
+const BDMV_DIRECTORY: &str = "BDMV";
+const MOVIE_OBJECT_FILE_NAME: &str = "MovieObject.bdmv";
+const BACKUP_DIRECTORY: &str = "BACKUP";
+const INDEX_FILE_NAME: &str = "index.bdmv";
+/// Signature of a zip local file header, to give a clearer error for zipped discs.
+const ZIP_HEADER: &[u8] = b"PK\x03\x04";
 
 /// Blu-Ray media region codes
 #[derive(Clone, Copy, Debug, ValueEnum)]
@@ -12,27 +44,84 @@ const MOVIE_OBJECT_HEADER: &[u8] = b"MOBJ0200";
 pub enum Region {
     /// North America, South America, U.S. Territories, Japan, South Korea, Taiwan, and other areas of
     /// Southeast Asia.
-    A = 1,
+    A,
     /// Europe, Africa, Middle East, Australia, and New Zealand.
-    B = 2,
+    B,
     /// Asia (except for Japan, Korea, Taiwan, and other areas of Southeast Asia)
-    C = 4,
+    C,
+    /// No region bits set. Some players treat this as region-free, but not all do, and depending
+    /// on how a disc compares the region, it may match either nothing or everything.
+    #[value(alias = "NONE")]
+    Free,
+}
+
+impl Region {
+    /// The value a player reports in PSR20 for this region. PSR20 is a bitmask rather than an
+    /// index: bit 0 is region A, bit 1 is region B, and bit 2 is region C.
+    pub fn to_psr_value(self) -> u32 {
+        match self {
+            Region::A => 0x1,
+            Region::B => 0x2,
+            Region::C => 0x4,
+            Region::Free => 0x0,
+        }
+    }
+
+    /// The regions whose bits are set in a PSR20 value.
+    pub fn from_psr_value(value: u32) -> Vec<Region> {
+        Region::value_variants()
+            .iter()
+            .copied()
+            .filter(|region| value & region.to_psr_value() != 0)
+            .collect()
+    }
+}
+
+/// MovieObject.bdmv format versions, as stored in bytes 4..8 of the header.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Version {
+    #[value(name = "0100")]
+    V0100,
+    #[value(name = "0200")]
+    V0200,
+}
+
+impl Version {
+    fn as_bytes(self) -> &'static [u8; 4] {
+        match self {
+            Version::V0100 => b"0100",
+            Version::V0200 => b"0200",
+        }
+    }
+
+    fn from_bytes(bytes: &[u8; 4]) -> Option<Version> {
+        Version::value_variants()
+            .iter()
+            .copied()
+            .find(|version| version.as_bytes() == bytes)
+    }
 }
 
 #[derive(Debug, Error)]
 pub enum OpenError {
     #[error("IO error for {0}")]
     IoError(PathBuf, #[source] std::io::Error),
+    #[error("{0} does not look like a Blu-Ray disc: expected a BDMV directory under it")]
+    NotABluRay(PathBuf),
     #[error("invalid MovieObject.bdmv: header too short")]
     TruncatedHeader,
     #[error("invalid MovieObject.bdmv header: {0:#04x?}")]
     BadMagicBytes([u8; 8]),
+    #[error("zip archives are not supported; extract BDMV/MovieObject.bdmv from it first")]
+    ZipArchive,
     #[error("invalid MovieObject.bdmv header: no length for movie objects")]
     MovieObjectsNoLength,
     #[error("invalid MovieObject.bdmv header: no reserved bytes for movie objects")]
     MovieObjectsNoReservedBytes,
     #[error("invalid MovieObject.bdmv header: no count of movie objects")]
     MovieObjectsNoCount,
+    #[error("invalid MovieObject.bdmv: movie objects need more than the declared {0} bytes")]
+    MovieObjectsOverflow(u32),
     #[error("invalid MovieObject.bdmv: movie object missing flags")]
     MovieObjectNoFlags,
     #[error("invalid MovieObject.bdmv: movie object missing navigation commands count")]
@@ -43,8 +132,12 @@ pub enum OpenError {
         "invalid MovieObject.bdmv: movie object #{0} navigation command #{1} is invalid: {2:#04x?}"
     )]
     NavigationCommandInvalid(u16, u16, #[source] NavigationCommandParseError),
-    #[error("unsupported MovieObject.bdmv: re-serialization roundtrip safety check failed")]
-    MovieObjectFileUnsupported,
+    #[error(
+        "unsupported MovieObject.bdmv: re-serialization roundtrip safety check failed at offset {0:#x}"
+    )]
+    MovieObjectFileUnsupported(usize),
+    #[error("unusual MovieObject.bdmv rejected in strict mode")]
+    Strict(#[source] Warning),
 }
 
 #[derive(Debug)]
@@ -56,6 +149,9 @@ pub struct MovieObjectFile {
     pub header: [u8; 40],
     pub movie_objects: MovieObjects,
     pub extension_data: Vec<u8>,
+    /// If a truncated trailing navigation command was dropped while recovering: its movie object
+    /// and navigation command indices, and the number of bytes dropped.
+    dropped_tail: Option<(u16, u16, usize)>,
 }
 
 impl MovieObjectFile {
@@ -64,6 +160,15 @@ impl MovieObjectFile {
         bytes.extend_from_slice(&self.header);
         bytes.extend_from_slice(&self.movie_objects.byte_len.to_be_bytes());
         bytes.extend_from_slice(&self.movie_objects.reserved);
+        bytes.extend_from_slice(&self.serialize_body());
+        bytes.extend_from_slice(&self.extension_data);
+        bytes
+    }
+
+    /// Serializes only the movie objects count and the movie objects, without the header, the
+    /// movie objects length and reserved bytes, or the extension data.
+    pub fn serialize_body(&self) -> Vec<u8> {
+        let mut bytes = vec![];
         bytes.extend_from_slice(
             &u16::try_from(self.movie_objects.movie_objects.len())
                 .unwrap()
@@ -77,12 +182,68 @@ impl MovieObjectFile {
                     .to_be_bytes(),
             );
             for navigation_command in &movie_object.navigation_commands {
-                bytes.extend_from_slice(&navigation_command.raw_bytes);
+                bytes.extend_from_slice(&navigation_command.to_bytes());
             }
         }
-        bytes.extend_from_slice(&self.extension_data);
         bytes
     }
+
+    /// Looks up a navigation command by its movie object and navigation command indices.
+    pub fn command(
+        &self,
+        movie_object_index: u16,
+        navigation_command_index: u16,
+    ) -> Option<&NavigationCommand> {
+        self.movie_objects
+            .movie_objects
+            .get(usize::from(movie_object_index))?
+            .navigation_commands
+            .get(usize::from(navigation_command_index))
+    }
+
+    /// The version stored in the header, e.g. "0200".
+    pub fn version(&self) -> &[u8; 4] {
+        self.header[4..8].try_into().unwrap()
+    }
+
+    /// Overrides the version stored in the header; `serialize` will emit this version.
+    pub fn set_version(&mut self, version: Version) {
+        self.header[4..8].copy_from_slice(version.as_bytes());
+    }
+
+    /// Creates a minimal file with the given number of movie objects, each consisting of the
+    /// given number of nops, and no extension data. Returns `None` if the movie objects would be
+    /// too large for the format.
+    pub fn blank(movie_objects_count: u16, navigation_commands_count: u16) -> Option<Self> {
+        let mut header = [0; 40];
+        header[..4].copy_from_slice(MOVIE_OBJECT_TYPE);
+        header[4..8].copy_from_slice(Version::V0200.as_bytes());
+        // The reserved bytes and count, then the flags, count, and commands of each movie object.
+        let byte_len = u32::try_from(
+            4 + 2
+                + u64::from(movie_objects_count)
+                    * (2 + 2 + 12 * u64::from(navigation_commands_count)),
+        )
+        .ok()?;
+        let nop = NavigationCommand::nop();
+        let movie_objects: Vec<_> = (0..movie_objects_count)
+            .map(|_| MovieObject {
+                header: 0,
+                navigation_commands: vec![nop; usize::from(navigation_commands_count)],
+                file_offset: None,
+            })
+            .collect();
+        Some(MovieObjectFile {
+            header,
+            movie_objects: MovieObjects {
+                byte_len,
+                reserved: [0; 4],
+                movie_objects,
+            },
+            extension_data: vec![],
+            dropped_tail: None,
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -106,6 +267,36 @@ pub struct MovieObject {
     /// The actual navigation commands. Note that the format reserves a big endian u16 before the
     /// actual navigation command that stores `navigation_commands.len()`.
     pub navigation_commands: Vec<NavigationCommand>,
+    file_offset: Option<usize>,
+}
+
+impl MovieObject {
+    const DOCUMENTED_FLAGS: [(u16, &'static str); 3] = [
+        (1 << 15, "resume intention"),
+        (1 << 14, "menu call mask"),
+        (1 << 13, "title search mask"),
+    ];
+
+    /// Where the movie object started in the file it was read from.
+    pub fn file_offset(&self) -> Option<usize> {
+        self.file_offset
+    }
+
+    /// The names of the documented flags that are set.
+    pub fn flag_names(&self) -> Vec<&'static str> {
+        Self::DOCUMENTED_FLAGS
+            .iter()
+            .filter(|(bit, _)| self.header & bit != 0)
+            .map(|&(_, name)| name)
+            .collect()
+    }
+
+    /// The flag bits that are set but reserved.
+    pub fn undocumented_flags(&self) -> u16 {
+        Self::DOCUMENTED_FLAGS
+            .iter()
+            .fold(self.header, |flags, (bit, _)| flags & !bit)
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -115,6 +306,7 @@ pub struct NavigationCommand {
     pub destination: Operand,
     pub source: Operand,
     pub raw_bytes: [u8; 12],
+    file_offset: Option<usize>,
 }
 
 #[derive(Debug, Error)]
@@ -130,46 +322,36 @@ pub enum NavigationCommandParseError {
 impl NavigationCommand {
     // TODO: This can only return certain errors, so maybe the error type should be more specific.
     pub fn from_bytes(bytes: &[u8; 12]) -> Result<Self, NavigationCommandParseError> {
-        let destination = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
-        let source = u32::from_be_bytes(bytes[8..12].try_into().unwrap());
+        let raw = RawCommand::from_bytes(bytes);
 
-        let operand_count = (bytes[0] >> 5) & 0x7;
-        let operand_count = match operand_count {
+        let operand_count = match raw.operand_count {
             0 => Ok(OperandCount::None),
             1 => Ok(OperandCount::DestinationOnly),
             2 => Ok(OperandCount::DestinationAndSource),
-            _ => Err(NavigationCommandParseError::BadOperandCount(operand_count)),
+            _ => Err(NavigationCommandParseError::BadOperandCount(
+                raw.operand_count,
+            )),
         }?;
-        let command_group = (bytes[0] >> 3) & 0x3;
-        let command_sub_group = bytes[0] & 0x7;
-
-        let destination_is_immediate_value = (bytes[1] & (1 << 7)) != 0;
-        let source_is_immediate_value = (bytes[1] & (1 << 6)) != 0;
-        let branch_option = bytes[1] & 0xf;
-
-        let compare_option = bytes[2] & 0xf;
-
-        let set_option = bytes[3] & 0x1f;
 
         let command = decode_command(
-            command_group,
-            command_sub_group,
-            branch_option,
-            compare_option,
-            set_option,
+            raw.command_group,
+            raw.command_sub_group,
+            raw.branch_option,
+            raw.compare_option,
+            raw.set_option,
         )
         .ok_or(NavigationCommandParseError::DecodeFailed(*bytes))?;
 
-        let destination = if destination_is_immediate_value {
-            Operand::Immediate(destination)
+        let destination = if raw.destination_is_immediate_value {
+            Operand::Immediate(raw.destination)
         } else {
-            Operand::new_register(destination)
+            Operand::new_register(raw.destination)
         };
 
-        let source = if source_is_immediate_value {
-            Operand::Immediate(source)
+        let source = if raw.source_is_immediate_value {
+            Operand::Immediate(raw.source)
         } else {
-            Operand::new_register(source)
+            Operand::new_register(raw.source)
         };
         Ok(Self {
             command,
@@ -177,7 +359,61 @@ impl NavigationCommand {
             destination,
             source,
             raw_bytes: *bytes,
+            file_offset: None,
+        })
+    }
+
+    /// Where the command was in the file it was read from. This is `None` for commands that
+    /// were not read from a file, e.g. inserted or patched ones.
+    pub fn file_offset(&self) -> Option<usize> {
+        self.file_offset
+    }
+
+    /// The canonical nop: all zero bytes.
+    pub fn nop() -> Self {
+        Self::from_bytes(&[0; 12]).unwrap()
+    }
+
+    /// An unconditional jump to navigation command `target` of the same movie object.
+    pub fn goto(target: u16) -> Self {
+        let (command_group, command_sub_group, branch_option, compare_option, set_option) =
+            encode_command(Command::Branch(Branch::GoTo));
+        Self::from_raw(RawCommand {
+            operand_count: 1,
+            command_group,
+            command_sub_group,
+            destination_is_immediate_value: true,
+            source_is_immediate_value: false,
+            branch_option,
+            compare_option,
+            set_option,
+            destination: target.into(),
+            source: 0,
+            reserved: [0; 3],
         })
+        .unwrap()
+    }
+
+    pub fn from_raw(raw: RawCommand) -> Result<Self, NavigationCommandParseError> {
+        Self::from_bytes(&raw.to_bytes())
+    }
+
+    /// Encodes the command for serialization. The operand count bits are derived from
+    /// `operand_count`, so they stay correct even if it was changed without updating the raw
+    /// bytes; everything else comes from the raw bytes.
+    pub fn to_bytes(self) -> [u8; 12] {
+        let mut raw = self.raw();
+        raw.operand_count = match self.operand_count {
+            OperandCount::None => 0,
+            OperandCount::DestinationOnly => 1,
+            OperandCount::DestinationAndSource => 2,
+        };
+        raw.to_bytes()
+    }
+
+    /// The bitfields of this command, for inspecting or patching it.
+    pub fn raw(&self) -> RawCommand {
+        RawCommand::from_bytes(&self.raw_bytes)
     }
 }
 
@@ -196,14 +432,22 @@ impl std::fmt::Debug for NavigationCommand {
 }
 
 #[allow(dead_code)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Command {
     Branch(Branch),
     Compare(Compare),
     Set(Set),
+    /// An option that the spec reserves within a known group and sub-group, e.g. set option 0.
+    /// Players ignore these rather than rejecting the disc. `options` is the branch, compare, or
+    /// set option, depending on the group.
+    Reserved {
+        group: u8,
+        sub_group: u8,
+        options: u8,
+    },
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Branch {
     Nop,
     GoTo,
@@ -221,7 +465,7 @@ pub enum Branch {
     LinkMark,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Compare {
     Bc,
     Eq,
@@ -233,7 +477,7 @@ pub enum Compare {
 }
 
 #[allow(clippy::enum_variant_names)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Set {
     Move,
     Swap,
@@ -337,6 +581,79 @@ pub enum Operand {
 }
 
 impl Operand {
+    /// The immediate value, if this is an immediate.
+    pub fn as_u32(self) -> Option<u32> {
+        match self {
+            Operand::Immediate(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// A short description of what a known PSR holds, e.g. "region" for PSR20.
+    pub fn psr_role(&self) -> Option<&'static str> {
+        let Operand::Psr(num) = self else {
+            return None;
+        };
+        Some(match num {
+            0 => "interactive graphics stream",
+            1 => "primary audio stream",
+            2 => "PG TextST stream",
+            3 => "angle",
+            4 => "title",
+            5 => "chapter",
+            6 => "play list",
+            7 => "play item",
+            8 => "presentation time",
+            9 => "timer",
+            10 => "selected button",
+            11 => "menu page",
+            12 => "TextST user style",
+            13 => "parental level",
+            14 => "secondary audio/video stream",
+            15 => "audio capability",
+            16 => "audio language",
+            17 => "PG and TextST language",
+            18 => "menu language",
+            19 => "country",
+            20 => "region",
+            29 => "video capability",
+            30 => "TextST capability",
+            31 => "player profile and version",
+            36 => "backup title",
+            37 => "backup chapter",
+            38 => "backup play list",
+            39 => "backup play item",
+            40 => "backup presentation time",
+            42 => "backup selected button",
+            43 => "backup menu page",
+            44 => "backup TextST user style",
+            48..=61 => "TextST language capability",
+            96 | 97 => "playlist indicator",
+            _ => return None,
+        })
+    }
+
+    /// The immediate value reinterpreted as a two's complement signed integer, e.g. for
+    /// arithmetic that relies on wrapping.
+    pub fn as_i32(self) -> Option<i32> {
+        self.as_u32().map(|value| value as i32)
+    }
+
+    /// The immediate interpreted as a PSR19 country code: two uppercase ASCII letters in the low
+    /// two bytes.
+    pub fn as_ascii_pair(self) -> Option<String> {
+        let bytes = self.as_u32()?.to_be_bytes();
+        bytes[2..4]
+            .iter()
+            .all(u8::is_ascii_uppercase)
+            .then(|| String::from_utf8_lossy(&bytes[2..4]).into_owned())
+    }
+
+    /// The immediate interpreted as a PSR20 region bitmask.
+    pub fn as_region_mask(self) -> Option<Vec<Region>> {
+        self.as_u32().map(Region::from_psr_value)
+    }
+
     fn new_register(num: u32) -> Operand {
         if (num & 0x80000000) != 0 {
             let num = num & !0x80000000;
@@ -353,34 +670,126 @@ impl Operand {
     }
 }
 
+/// Resolves a path to a MovieObject.bdmv file. The path may be the file itself or the top-level
+/// directory of a disc, i.e. the directory that contains the BDMV directory. Since extracted discs
+/// don't always keep the case of names, e.g. "bdmv/movieobject.bdmv", names are matched
+/// case-insensitively if there's no exact match.
+pub fn resolve_path(path: &Path) -> Result<PathBuf, OpenError> {
+    if !path.is_dir() {
+        return Ok(path.to_owned());
+    }
+    let bdmv = find_case_insensitive(path, BDMV_DIRECTORY)
+        .filter(|bdmv| bdmv.is_dir())
+        .ok_or_else(|| OpenError::NotABluRay(path.to_owned()))?;
+    Ok(find_case_insensitive(&bdmv, MOVIE_OBJECT_FILE_NAME)
+        .unwrap_or_else(|| bdmv.join(MOVIE_OBJECT_FILE_NAME)))
+}
+
+/// Finds the entry in `dir` named `name`, preferring an exact match over one that differs only
+/// in ASCII case.
+fn find_case_insensitive(dir: &Path, name: &str) -> Option<PathBuf> {
+    let exact = dir.join(name);
+    if exact.exists() {
+        return Some(exact);
+    }
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .find(|entry| entry.file_name().eq_ignore_ascii_case(name))
+        .map(|entry| entry.path())
+}
+
+/// Returns the path of the backup copy of a MovieObject.bdmv file, i.e.
+/// BDMV/BACKUP/MovieObject.bdmv for BDMV/MovieObject.bdmv.
+pub fn backup_path(path: &Path) -> PathBuf {
+    path.with_file_name(BACKUP_DIRECTORY)
+        .join(MOVIE_OBJECT_FILE_NAME)
+}
+
+/// Returns the path of the index.bdmv next to a MovieObject.bdmv file, matching its name
+/// case-insensitively like [`resolve_path`].
+pub fn index_path(path: &Path) -> PathBuf {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    find_case_insensitive(dir, INDEX_FILE_NAME).unwrap_or_else(|| dir.join(INDEX_FILE_NAME))
+}
+
 impl MovieObjectFile {
+    /// Fails if there are any warnings about the parsed file.
+    pub fn strict(self) -> Result<Self, OpenError> {
+        match self.warnings().into_iter().next() {
+            Some(warning) => Err(OpenError::Strict(warning)),
+            None => Ok(self),
+        }
+    }
+
     pub fn open(path: &Path) -> Result<Self, OpenError> {
-        let mut movie_object_file =
-            File::open(path).map_err(|e| OpenError::IoError(path.to_owned(), e))?;
+        Self::from_bytes(&read(path)?)
+    }
+
+    /// Like `open`, but see `from_bytes_recovering`.
+    pub fn open_recovering(path: &Path) -> Result<Self, OpenError> {
+        Self::from_bytes_recovering(&read(path)?)
+    }
+
+    /// Reads only the number of movie objects from a MovieObject.bdmv file, without decoding
+    /// any of them.
+    pub fn read_movie_objects_count(path: &Path) -> Result<u16, OpenError> {
         let mut contents = vec![];
-        movie_object_file
-            .read_to_end(&mut contents)
+        File::open(path)
+            .and_then(|file| file.take(40 + 4 + 4 + 2).read_to_end(&mut contents))
             .map_err(|e| OpenError::IoError(path.to_owned(), e))?;
-        let contents = contents;
-        // First 40 bytes are the header. Most of the header isn't interesting here, but check
-        // the first 8 bytes which contain a magic signature.
-        let (header, remainder) = contents
-            .split_first_chunk::<40>()
-            .ok_or(OpenError::TruncatedHeader)?;
-        let magic_bytes = header.first_chunk::<8>().unwrap();
-        if magic_bytes != MOVIE_OBJECT_HEADER {
-            return Err(OpenError::BadMagicBytes(*magic_bytes));
-        }
+        let (_, remainder) = split_header(&contents)?;
+        let (_, remainder) = remainder
+            .split_first_chunk::<4>()
+            .ok_or(OpenError::MovieObjectsNoLength)?;
+        let (_, remainder) = remainder
+            .split_first_chunk::<4>()
+            .ok_or(OpenError::MovieObjectsNoReservedBytes)?;
+        let (movie_objects_count, _) = remainder
+            .split_first_chunk::<2>()
+            .ok_or(OpenError::MovieObjectsNoCount)?;
+        Ok(u16::from_be_bytes(*movie_objects_count))
+    }
+
+    /// Parses the contents of a MovieObject.bdmv file.
+    pub fn from_bytes(contents: &[u8]) -> Result<Self, OpenError> {
+        Self::parse(contents, false)
+    }
+
+    /// Like `from_bytes`, but if the file ends partway through a navigation command, keeps
+    /// everything before it instead of failing. The dropped bytes are reported as a warning, and
+    /// `serialize` writes only what was kept, with the counts and lengths fixed to match.
+    pub fn from_bytes_recovering(contents: &[u8]) -> Result<Self, OpenError> {
+        Self::parse(contents, true)
+    }
+
+    fn parse(contents: &[u8], recover: bool) -> Result<Self, OpenError> {
+        let (header, remainder) = split_header(contents)?;
         let (movie_objects_len, remainder) = remainder
             .split_first_chunk::<4>()
             .ok_or(OpenError::MovieObjectsNoLength)?;
         let movie_objects_len = u32::from_be_bytes(*movie_objects_len);
+        // Only parse within the declared length, so that a malformed count can't cause extension
+        // data to be misread as navigation commands.
+        let (remainder, trailing) = remainder.split_at(
+            usize::try_from(movie_objects_len)
+                .unwrap_or(usize::MAX)
+                .min(remainder.len()),
+        );
+        // Running out of bytes is only an overflow if the file itself has more.
+        let overflow = |err| {
+            if trailing.is_empty() {
+                err
+            } else {
+                OpenError::MovieObjectsOverflow(movie_objects_len)
+            }
+        };
         let (movie_objects_reserved, remainder) = remainder
             .split_first_chunk::<4>()
-            .ok_or(OpenError::MovieObjectsNoReservedBytes)?;
+            .ok_or_else(|| overflow(OpenError::MovieObjectsNoReservedBytes))?;
         let (movie_objects_count, remainder) = remainder
             .split_first_chunk::<2>()
-            .ok_or(OpenError::MovieObjectsNoCount)?;
+            .ok_or_else(|| overflow(OpenError::MovieObjectsNoCount))?;
         let movie_objects_count = u16::from_be_bytes(*movie_objects_count);
 
         let mut movie_object_file = MovieObjectFile {
@@ -392,32 +801,43 @@ impl MovieObjectFile {
                 movie_objects: vec![],
             },
             extension_data: vec![],
+            dropped_tail: None,
         };
         let mut unparsed = remainder;
+        let offset = |unparsed: &[u8]| contents.len() - trailing.len() - unparsed.len();
         for i in 0..movie_objects_count {
+            let file_offset = offset(unparsed);
             let (flags, remainder) = unparsed
                 .split_first_chunk::<2>()
-                .ok_or(OpenError::MovieObjectNoFlags)?;
+                .ok_or_else(|| overflow(OpenError::MovieObjectNoFlags))?;
             unparsed = remainder;
             let header = u16::from_be_bytes(*flags);
             let (navigation_commands_count, remainder) = unparsed
                 .split_first_chunk::<2>()
-                .ok_or(OpenError::NavigationCommandsNoCount)?;
+                .ok_or_else(|| overflow(OpenError::NavigationCommandsNoCount))?;
             unparsed = remainder;
             let navigation_commands_count = u16::from_be_bytes(*navigation_commands_count);
 
             let mut navigation_commands = vec![];
             for j in 0..navigation_commands_count {
                 // Each navigation command should be exactly 12 bytes.
-                let (bytes, remainder) = unparsed
-                    .split_first_chunk::<12>()
-                    .ok_or(OpenError::NavigationCommandTruncated(i, j))?;
+                let file_offset = offset(unparsed);
+                let Some((bytes, remainder)) = unparsed.split_first_chunk::<12>() else {
+                    // Only a truncated file can be recovered, not a malformed count.
+                    if recover && trailing.is_empty() {
+                        movie_object_file.dropped_tail = Some((i, j, unparsed.len()));
+                        unparsed = &[];
+                        break;
+                    }
+                    return Err(overflow(OpenError::NavigationCommandTruncated(i, j)));
+                };
                 unparsed = remainder;
 
-                navigation_commands.push(
-                    NavigationCommand::from_bytes(bytes)
-                        .map_err(|err| OpenError::NavigationCommandInvalid(i, j, err))?,
-                );
+                navigation_commands.push(NavigationCommand {
+                    file_offset: Some(file_offset),
+                    ..NavigationCommand::from_bytes(bytes)
+                        .map_err(|err| OpenError::NavigationCommandInvalid(i, j, err))?
+                });
             }
 
             movie_object_file
@@ -426,20 +846,68 @@ impl MovieObjectFile {
                 .push(MovieObject {
                     header,
                     navigation_commands,
+                    file_offset: Some(file_offset),
                 });
+            if movie_object_file.dropped_tail.is_some() {
+                break;
+            }
         }
 
         // Assume all unconsumed data is extension data.
-        movie_object_file.extension_data = unparsed.to_vec();
+        movie_object_file.extension_data = [unparsed, trailing].concat();
 
-        if movie_object_file.serialize() == contents {
-            Ok(movie_object_file)
-        } else {
-            Err(OpenError::MovieObjectFileUnsupported)
+        // The recovered file can't round-trip, since its tail is gone. There's no extension data
+        // after a truncated command either.
+        if movie_object_file.dropped_tail.is_some() {
+            movie_object_file.movie_objects.byte_len =
+                u32::try_from(4 + movie_object_file.serialize_body().len()).unwrap();
+            movie_object_file.header[8..12].fill(0);
+            return Ok(movie_object_file);
+        }
+
+        let serialized = movie_object_file.serialize();
+        match serialized
+            .iter()
+            .zip(contents)
+            .position(|(serialized, original)| serialized != original)
+        {
+            Some(offset) => Err(OpenError::MovieObjectFileUnsupported(offset)),
+            None if serialized.len() != contents.len() => Err(
+                OpenError::MovieObjectFileUnsupported(serialized.len().min(contents.len())),
+            ),
+            None => Ok(movie_object_file),
         }
     }
 }
 
+fn read(path: &Path) -> Result<Vec<u8>, OpenError> {
+    let mut contents = vec![];
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut contents))
+        .map_err(|e| OpenError::IoError(path.to_owned(), e))?;
+    Ok(contents)
+}
+
+/// Splits off the 40-byte header, checking its magic signature.
+fn split_header(contents: &[u8]) -> Result<(&[u8; 40], &[u8]), OpenError> {
+    // Most of the header isn't interesting here, but check the first 8 bytes which contain a
+    // magic signature: the type indicator and a known version.
+    let (header, remainder) = contents
+        .split_first_chunk::<40>()
+        .ok_or(OpenError::TruncatedHeader)?;
+    let magic_bytes = header.first_chunk::<8>().unwrap();
+    if magic_bytes.starts_with(ZIP_HEADER) {
+        return Err(OpenError::ZipArchive);
+    }
+    let (type_indicator, version) = magic_bytes.split_at(4);
+    if type_indicator != MOVIE_OBJECT_TYPE
+        || Version::from_bytes(version.try_into().unwrap()).is_none()
+    {
+        return Err(OpenError::BadMagicBytes(*magic_bytes));
+    }
+    Ok((header, remainder))
+}
+
 fn decode_command(
     command_group: u8,
     command_sub_group: u8,
@@ -502,7 +970,434 @@ fn decode_command(
             (2, 1, _, _, 0x7) => Command::Set(Set::PopupOff),
             (2, 1, _, _, 0x8) => Command::Set(Set::StillOn),
             (2, 1, _, _, 0x9) => Command::Set(Set::StillOff),
+            (0, 0..=2, options, _, _) | (1, _, _, options, _) | (2, 0 | 1, _, _, options) => {
+                Command::Reserved {
+                    group: command_group,
+                    sub_group: command_sub_group,
+                    options,
+                }
+            }
             _ => return None,
         },
     )
 }
+
+/// The inverse of `decode_command`.
+fn encode_command(command: Command) -> (u8, u8, u8, u8, u8) {
+    match command {
+        Command::Branch(branch) => {
+            let (command_sub_group, branch_option) = match branch {
+                Branch::Nop => (0, 0),
+                Branch::GoTo => (0, 1),
+                Branch::Break => (0, 2),
+                Branch::JumpObject => (1, 0),
+                Branch::JumpTitle => (1, 1),
+                Branch::CallObject => (1, 2),
+                Branch::CallTitle => (1, 3),
+                Branch::Resume => (1, 4),
+                Branch::PlayList => (2, 0),
+                Branch::PlayItem => (2, 1),
+                Branch::PlayMark => (2, 2),
+                Branch::Terminate => (2, 3),
+                Branch::LinkItem => (2, 4),
+                Branch::LinkMark => (2, 5),
+            };
+            (0, command_sub_group, branch_option, 0, 0)
+        }
+        Command::Compare(compare) => {
+            let compare_option = match compare {
+                Compare::Bc => 1,
+                Compare::Eq => 2,
+                Compare::Ne => 3,
+                Compare::Ge => 4,
+                Compare::Gt => 5,
+                Compare::Le => 6,
+                Compare::Lt => 7,
+            };
+            (1, 0, 0, compare_option, 0)
+        }
+        Command::Set(set) => {
+            let (command_sub_group, set_option) = match set {
+                Set::Move => (0, 0x1),
+                Set::Swap => (0, 0x2),
+                Set::Add => (0, 0x3),
+                Set::Sub => (0, 0x4),
+                Set::Mul => (0, 0x5),
+                Set::Div => (0, 0x6),
+                Set::Mod => (0, 0x7),
+                Set::Rnd => (0, 0x8),
+                Set::And => (0, 0x9),
+                Set::Or => (0, 0xa),
+                Set::Xor => (0, 0xb),
+                Set::Bitset => (0, 0xc),
+                Set::Bitclr => (0, 0xd),
+                Set::ShiftLeft => (0, 0xe),
+                Set::ShiftRight => (0, 0xf),
+                Set::SetStream => (1, 0x1),
+                Set::SetNVTimer => (1, 0x2),
+                Set::ButtonPage => (1, 0x3),
+                Set::EnableButton => (1, 0x4),
+                Set::DisableButton => (1, 0x5),
+                Set::SetSecondaryStream => (1, 0x6),
+                Set::PopupOff => (1, 0x7),
+                Set::StillOn => (1, 0x8),
+                Set::StillOff => (1, 0x9),
+            };
+            (2, command_sub_group, 0, 0, set_option)
+        }
+        Command::Reserved {
+            group,
+            sub_group,
+            options,
+        } => match group {
+            0 => (group, sub_group, options, 0, 0),
+            1 => (group, sub_group, 0, options, 0),
+            _ => (group, sub_group, 0, 0, options),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Command, MovieObjectFile, NavigationCommand, OpenError, Operand, OperandCount, PatchAction,
+        Region, Version, Warning, resolve_path, testing,
+    };
+
+    #[test]
+    fn regions_are_psr20_bits() {
+        // PSR20 has one bit per region, so a disc playable in A and C is 0b101.
+        let values: Vec<_> = [Region::A, Region::B, Region::C, Region::Free]
+            .into_iter()
+            .map(Region::to_psr_value)
+            .collect();
+        assert_eq!(values, [0x1, 0x2, 0x4, 0x0]);
+        assert!(matches!(
+            Region::from_psr_value(0x5)[..],
+            [Region::A, Region::C]
+        ));
+        assert!(Region::from_psr_value(0).is_empty());
+    }
+
+    #[test]
+    fn immediates_have_typed_interpretations() {
+        let immediate = Operand::Immediate(0x4a50);
+        assert_eq!(immediate.as_u32(), Some(0x4a50));
+        assert_eq!(immediate.as_ascii_pair().as_deref(), Some("JP"));
+        assert_eq!(Operand::Immediate(0x7573).as_ascii_pair(), None);
+        assert_eq!(Operand::Immediate(0xffff_fffe).as_i32(), Some(-2));
+        assert_eq!(Operand::Immediate(7).as_i32(), Some(7));
+        assert!(matches!(
+            Operand::Immediate(0x3).as_region_mask().as_deref(),
+            Some([Region::A, Region::B])
+        ));
+        // Registers have no immediate value to interpret.
+        let register = Operand::Psr(20);
+        assert_eq!(register.as_u32(), None);
+        assert_eq!(register.as_i32(), None);
+        assert_eq!(register.as_ascii_pair(), None);
+        assert!(register.as_region_mask().is_none());
+    }
+
+    #[test]
+    fn psr_roles_name_locale_registers() {
+        assert_eq!(Operand::Psr(13).psr_role(), Some("parental level"));
+        assert_eq!(Operand::Psr(19).psr_role(), Some("country"));
+        assert_eq!(Operand::Psr(20).psr_role(), Some("region"));
+        assert_eq!(Operand::Psr(127).psr_role(), None);
+        assert_eq!(Operand::Gpr(20).psr_role(), None);
+        // Only the alternate form, used by dump, describes the role.
+        assert_eq!(format!("{:#}", Operand::Psr(20)), "psr20 (region)");
+        assert_eq!(Operand::Psr(20).to_string(), "psr20");
+    }
+
+    #[test]
+    fn body_excludes_header_and_extension_data() {
+        let bytes = testing::bytes(&[&["MOV gpr1, psr20"], &["NOP", "TERMINATE"]]);
+        let body_len = bytes.len() - 48;
+        let bytes = [&bytes[..], b"extension data"].concat();
+        let file = MovieObjectFile::from_bytes(&bytes).unwrap();
+        assert_eq!(file.extension_data, b"extension data");
+        assert_eq!(file.serialize(), bytes);
+        assert_eq!(file.serialize_body(), &bytes[48..48 + body_len]);
+    }
+
+    #[test]
+    fn offsets_follow_the_command_stride() {
+        let file = testing::file(&[&["NOP", "NOP", "NOP"], &["MOV gpr1, 2", "TERMINATE"]]);
+        let objects = &file.movie_objects.movie_objects;
+        // Each object has 2 bytes of flags and 2 of command count before its commands.
+        let object_offsets: Vec<_> = objects.iter().map(|object| object.file_offset()).collect();
+        assert_eq!(object_offsets, [Some(50), Some(50 + 4 + 3 * 12)]);
+        for object in objects {
+            let offsets: Vec<_> = object
+                .navigation_commands
+                .iter()
+                .map(|command| command.file_offset().unwrap())
+                .collect();
+            let start = object.file_offset().unwrap() + 4;
+            assert_eq!(
+                offsets,
+                (0..offsets.len())
+                    .map(|j| start + 12 * j)
+                    .collect::<Vec<_>>()
+            );
+        }
+        // Patched commands weren't read from the file.
+        let mut file = file;
+        file.apply_patch(1, 0, PatchAction::Nop).unwrap();
+        assert_eq!(
+            file.movie_objects.movie_objects[1].navigation_commands[0].file_offset(),
+            None
+        );
+    }
+
+    #[test]
+    fn count_matches_full_parse_without_decoding() {
+        let dir = testing::scratch_directory("count");
+        let path = dir.join("MovieObject.bdmv");
+        let bytes = testing::bytes(&[&["NOP"], &["MOV gpr1, psr20", "EQ gpr1, 2"], &[]]);
+        std::fs::write(&path, &bytes).unwrap();
+        let count = MovieObjectFile::read_movie_objects_count(&path).unwrap();
+        assert_eq!(count, 3);
+        let file = MovieObjectFile::open(&path).unwrap();
+        assert_eq!(file.movie_objects.movie_objects.len(), usize::from(count));
+        // Nothing past the count is read, so the movie objects themselves may be missing.
+        std::fs::write(&path, &bytes[..50]).unwrap();
+        assert_eq!(MovieObjectFile::read_movie_objects_count(&path).unwrap(), 3);
+        assert!(MovieObjectFile::open(&path).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reserved_option_combinations_are_classified() {
+        // A compare with compare option 0, and a system set with set option 0xa.
+        let compare = [
+            0x48, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+        ];
+        let system_set = [
+            0x51, 0xc0, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let command = NavigationCommand::from_bytes(&compare).unwrap();
+        assert!(matches!(
+            command.command,
+            Command::Reserved {
+                group: 1,
+                sub_group: 0,
+                options: 0
+            }
+        ));
+        assert_eq!(command.to_bytes(), compare);
+        let command = NavigationCommand::from_bytes(&system_set).unwrap();
+        assert!(matches!(
+            command.command,
+            Command::Reserved {
+                group: 2,
+                sub_group: 1,
+                options: 0xa
+            }
+        ));
+        assert_eq!(command.to_bytes(), system_set);
+        // Command group 3 doesn't exist at all.
+        let mut invalid = compare;
+        invalid[0] = 0x58;
+        assert!(NavigationCommand::from_bytes(&invalid).is_err());
+
+        // A file with a reserved command opens, but fails in strict mode.
+        let mut bytes = testing::bytes(&[&["NOP"]]);
+        bytes[54..66].copy_from_slice(&compare);
+        let file = MovieObjectFile::from_bytes(&bytes).unwrap();
+        assert!(matches!(
+            file.strict(),
+            Err(OpenError::Strict(Warning::ReservedCommand(0, 0, _)))
+        ));
+        bytes[54..66].copy_from_slice(&invalid);
+        assert!(matches!(
+            MovieObjectFile::from_bytes(&bytes),
+            Err(OpenError::NavigationCommandInvalid(0, 0, _))
+        ));
+    }
+
+    #[test]
+    fn truncated_tail_is_recovered() {
+        let mut bytes =
+            testing::bytes(&[&["PLAY_PL 0"], &["MOV gpr1, psr20", "NE gpr1, 2", "NOP"]]);
+        // Cut the last command to 7 of its 12 bytes.
+        bytes.truncate(bytes.len() - 5);
+        assert!(matches!(
+            MovieObjectFile::from_bytes(&bytes),
+            Err(OpenError::NavigationCommandTruncated(1, 2))
+        ));
+        let file = MovieObjectFile::from_bytes_recovering(&bytes).unwrap();
+        assert!(matches!(
+            file.warnings()[..],
+            [Warning::TruncatedTail(1, 2, 7)]
+        ));
+        assert_eq!(
+            testing::assembly(&file, 1),
+            ["MOV gpr1, psr20", "NE gpr1, 2"]
+        );
+        // The counts and lengths are fixed to match what was kept.
+        let recovered = file.serialize();
+        assert_eq!(
+            recovered,
+            testing::bytes(&[&["PLAY_PL 0"], &["MOV gpr1, psr20", "NE gpr1, 2"]])
+        );
+        assert!(MovieObjectFile::from_bytes(&recovered).is_ok());
+    }
+
+    #[test]
+    fn counts_past_the_declared_length_are_rejected() {
+        let bytes = testing::bytes(&[&["NOP"], &["NOP"]]);
+        let declared = u32::try_from(bytes.len() - 44).unwrap();
+        let mut bytes = [&bytes[..], &[0; 16]].concat();
+        // One more movie object than there is room for, with extension data after them that
+        // must not be read as one.
+        bytes[49] = 3;
+        assert!(matches!(
+            MovieObjectFile::from_bytes(&bytes),
+            Err(OpenError::MovieObjectsOverflow(len)) if len == declared
+        ));
+        // So does a navigation commands count, here of the last movie object, at 66.
+        bytes[49] = 2;
+        bytes[69] = 2;
+        assert!(matches!(
+            MovieObjectFile::from_bytes(&bytes),
+            Err(OpenError::MovieObjectsOverflow(len)) if len == declared
+        ));
+    }
+
+    #[test]
+    fn undocumented_flag_bits_survive_serialization() {
+        let mut bytes = testing::bytes(&[&["NOP"], &["NOP"]]);
+        // Menu call mask and two low bits on the first movie object, and all bits on the second.
+        bytes[50..52].copy_from_slice(&[0x40, 0x05]);
+        bytes[66..68].copy_from_slice(&[0xff, 0xff]);
+        let file = MovieObjectFile::from_bytes(&bytes).unwrap();
+        let [first, second] = &file.movie_objects.movie_objects[..] else {
+            unreachable!()
+        };
+        assert_eq!(first.flag_names(), ["menu call mask"]);
+        assert_eq!(first.undocumented_flags(), 0x0005);
+        assert_eq!(second.undocumented_flags(), 0x1fff);
+        assert_eq!(file.serialize(), bytes);
+    }
+
+    #[test]
+    fn operand_count_bits_survive_patching() {
+        let mut file = testing::file(&[&["MOV gpr1, psr20", "EQ psr19, 0x4a50", "JUMP_OBJECT 1"]]);
+        file.apply_patch(0, 0, PatchAction::SetRegion(2)).unwrap();
+        file.apply_patch(0, 1, PatchAction::SetCountry(*b"US"))
+            .unwrap();
+        file.apply_patch(0, 2, PatchAction::Nop).unwrap();
+        let bytes = file.serialize();
+        let operand_counts: Vec<_> = bytes[54..]
+            .chunks_exact(12)
+            .map(|command| command[0] >> 5)
+            .collect();
+        assert_eq!(operand_counts, [2, 2, 0]);
+    }
+
+    #[test]
+    fn operand_count_bits_follow_operand_count() {
+        let mut command: NavigationCommand = "MOV gpr1, 2".parse().unwrap();
+        command.operand_count = OperandCount::DestinationOnly;
+        assert_eq!(command.to_bytes()[0] >> 5, 1);
+        // Only the operand count bits change.
+        assert_eq!(command.to_bytes()[0] & 0x1f, command.raw_bytes[0] & 0x1f);
+        assert_eq!(command.to_bytes()[1..], command.raw_bytes[1..]);
+    }
+
+    #[test]
+    fn assumed_version_is_emitted_and_parses_again() {
+        let mut file = testing::file(&[&["MOV gpr1, psr20", "NE gpr1, 2"]]);
+        file.set_version(Version::V0100);
+        let bytes = file.serialize();
+        assert_eq!(&bytes[..8], b"MOBJ0100");
+        let reparsed = MovieObjectFile::from_bytes(&bytes).unwrap();
+        assert_eq!(reparsed.version(), b"0100");
+        assert_eq!(reparsed.serialize(), bytes);
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let mut bytes = testing::bytes(&[&["NOP"]]);
+        bytes[4..8].copy_from_slice(b"0300");
+        assert!(matches!(
+            MovieObjectFile::from_bytes(&bytes),
+            Err(OpenError::BadMagicBytes(magic)) if &magic == b"MOBJ0300"
+        ));
+    }
+
+    #[test]
+    fn missing_bdmv_directory_is_not_a_disc() {
+        let dir = testing::scratch_directory("no-bdmv");
+        std::fs::create_dir(dir.join("CERTIFICATE")).unwrap();
+        assert!(matches!(
+            resolve_path(&dir),
+            Err(OpenError::NotABluRay(path)) if path == dir
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn names_are_matched_case_insensitively() {
+        let dir = testing::scratch_directory("lowercase");
+        std::fs::create_dir(dir.join("bdmv")).unwrap();
+        let expected = dir.join("bdmv").join("movieobject.bdmv");
+        std::fs::write(&expected, testing::bytes(&[&["NOP"]])).unwrap();
+        assert_eq!(resolve_path(&dir).unwrap(), expected);
+        assert!(MovieObjectFile::open(&resolve_path(&dir).unwrap()).is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+        // An exact match wins over one that only differs in case, where both can exist.
+        let dir = testing::scratch_directory("mixed-case");
+        std::fs::create_dir_all(dir.join("BDMV")).unwrap();
+        std::fs::write(dir.join("BDMV").join("MOVIEOBJECT.BDMV"), b"").unwrap();
+        std::fs::write(dir.join("BDMV").join("MovieObject.bdmv"), b"").unwrap();
+        assert_eq!(
+            resolve_path(&dir).unwrap(),
+            dir.join("BDMV").join("MovieObject.bdmv")
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unreadable_movie_object_file_names_its_path() {
+        let dir = testing::scratch_directory("unreadable");
+        std::fs::create_dir(dir.join("BDMV")).unwrap();
+        let expected = dir.join("BDMV").join("MovieObject.bdmv");
+        // The disc is found, but MovieObject.bdmv is missing.
+        let path = resolve_path(&dir).unwrap();
+        assert_eq!(path, expected);
+        assert!(matches!(
+            MovieObjectFile::open(&path),
+            Err(OpenError::IoError(path, _)) if path == expected
+        ));
+        // It's there, but can't be read as a file.
+        std::fs::create_dir(&expected).unwrap();
+        assert!(matches!(
+            MovieObjectFile::open(&path),
+            Err(OpenError::IoError(path, _)) if path == expected
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn zip_archive_is_rejected_clearly() {
+        // MovieObject.bdmv with a single nop, stored uncompressed in a zip archive.
+        let bytes = crate::hex::decode(concat!(
+            "504b0304140000000000000021503c9aa8554200000042000000100000004d6f",
+            "7669654f626a6563742e62646d764d4f424a3032303000000000000000000000",
+            "0000000000000000000000000000000000000000000000000016000000000001",
+            "00000001000000000000000000000000504b0102140314000000000000002150",
+            "3c9aa85542000000420000001000000000000000000000008001000000004d6f",
+            "7669654f626a6563742e62646d76504b050600000000010001003e0000007000",
+            "00000000",
+        ))
+        .unwrap();
+        assert!(matches!(
+            MovieObjectFile::from_bytes(&bytes),
+            Err(OpenError::ZipArchive)
+        ));
+    }
+}
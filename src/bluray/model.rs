@@ -0,0 +1,171 @@
+use super::arguments::Arguments;
+use super::{MovieObjectFile, NavigationCommand};
+use std::ops::Range;
+
+/// A decoded MovieObject.bdmv, with everything a front-end would otherwise derive itself from
+/// the parse types.
+#[derive(Debug)]
+pub struct DiscModel {
+    /// The format version from the header, e.g. "0200".
+    pub version: String,
+    pub movie_objects: Vec<MovieObjectModel>,
+}
+
+#[derive(Debug)]
+pub struct MovieObjectModel {
+    pub index: u16,
+    /// Where the movie object starts in the file it was read from.
+    pub file_offset: Option<usize>,
+    /// The full 16-bit flags field.
+    pub flags: u16,
+    /// The names of the documented flags that are set.
+    pub flag_names: Vec<&'static str>,
+    /// The flag bits that are set but reserved.
+    pub undocumented_flags: u16,
+    pub navigation_commands: Vec<CommandModel>,
+}
+
+#[derive(Debug)]
+pub struct CommandModel {
+    pub index: u16,
+    /// Where the command is in the file it was read from.
+    pub file_offset: Option<usize>,
+    pub command: NavigationCommand,
+    /// The command as assembly, with known PSRs described, e.g. "MOV gpr1, psr20 (region)".
+    pub assembly: String,
+    /// The decoded operands of commands with a command-specific operand layout.
+    pub arguments: Option<Arguments>,
+}
+
+/// A section of the serialized file.
+#[derive(Debug)]
+pub struct Section {
+    pub name: &'static str,
+    pub range: Range<usize>,
+}
+
+impl MovieObjectFile {
+    /// The byte ranges of each section of the serialized file, in order. The extension data
+    /// includes anything after the movie objects, even if the declared movie objects length
+    /// covers it.
+    pub fn layout(&self) -> Vec<Section> {
+        let body_end = 48 + self.serialize_body().len();
+        [
+            ("type indicator and version", 0..8),
+            ("extension data start address", 8..12),
+            ("reserved", 12..40),
+            ("movie objects length", 40..44),
+            ("movie objects reserved", 44..48),
+            ("movie objects count", 48..50),
+            ("movie objects", 50..body_end),
+            (
+                "extension data",
+                body_end..body_end + self.extension_data.len(),
+            ),
+        ]
+        .into_iter()
+        .map(|(name, range)| Section { name, range })
+        .collect()
+    }
+
+    /// Builds the decoded model of the whole file.
+    pub fn model(&self) -> DiscModel {
+        DiscModel {
+            version: String::from_utf8_lossy(self.version()).into_owned(),
+            movie_objects: (0..)
+                .zip(&self.movie_objects.movie_objects)
+                .map(|(index, object)| MovieObjectModel {
+                    index,
+                    file_offset: object.file_offset(),
+                    flags: object.header,
+                    flag_names: object.flag_names(),
+                    undocumented_flags: object.undocumented_flags(),
+                    navigation_commands: (0..)
+                        .zip(&object.navigation_commands)
+                        .map(|(index, &command)| CommandModel {
+                            index,
+                            file_offset: command.file_offset(),
+                            command,
+                            assembly: format!("{command:#}"),
+                            arguments: command.arguments(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bluray::{MovieObjectFile, testing};
+
+    #[test]
+    fn model_has_decoded_fields_and_offsets() {
+        let mut bytes = testing::bytes(&[&["MOV gpr1, psr20", "SET_NV_TIMER 1, 60"], &[]]);
+        // Resume intention, plus an undocumented low bit.
+        bytes[50..52].copy_from_slice(&[0x80, 0x01]);
+        let model = MovieObjectFile::from_bytes(&bytes).unwrap().model();
+        assert_eq!(model.version, "0200");
+        assert_eq!(model.movie_objects.len(), 2);
+        let object = &model.movie_objects[0];
+        assert_eq!(
+            (object.index, object.file_offset, object.flags),
+            (0, Some(50), 0x8001)
+        );
+        assert_eq!(object.flag_names, ["resume intention"]);
+        assert_eq!(object.undocumented_flags, 0x0001);
+        let commands: Vec<_> = object
+            .navigation_commands
+            .iter()
+            .map(|command| {
+                (
+                    command.index,
+                    command.file_offset,
+                    command.assembly.as_str(),
+                    command.arguments.map(|arguments| arguments.to_string()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                (0, Some(54), "MOV gpr1, psr20 (region)", None),
+                (
+                    1,
+                    Some(66),
+                    "SET_NV_TIMER 1, 60",
+                    Some("NvTimer movie_object=1 timeout=60s".to_string())
+                ),
+            ]
+        );
+        assert!(model.movie_objects[1].navigation_commands.is_empty());
+    }
+
+    #[test]
+    fn layout_covers_the_file_in_order() {
+        let bytes = testing::bytes(&[&["NOP", "NOP"]]);
+        let bytes = [&bytes[..], b"ext"].concat();
+        let file = MovieObjectFile::from_bytes(&bytes).unwrap();
+        let layout: Vec<_> = file
+            .layout()
+            .into_iter()
+            .map(|section| (section.name, section.range))
+            .collect();
+        // The one movie object is 2 bytes of flags, 2 of command count, and two 12-byte commands.
+        assert_eq!(
+            layout,
+            [
+                ("type indicator and version", 0..8),
+                ("extension data start address", 8..12),
+                ("reserved", 12..40),
+                ("movie objects length", 40..44),
+                ("movie objects reserved", 44..48),
+                ("movie objects count", 48..50),
+                ("movie objects", 50..78),
+                ("extension data", 78..81),
+            ]
+        );
+        assert_eq!(layout.last().unwrap().1.end, bytes.len());
+    }
+}
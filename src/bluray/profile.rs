@@ -0,0 +1,158 @@
+use super::{Command, MovieObjectFile, Operand, OperandCount, Set};
+use std::collections::HashSet;
+
+/// Profile bits in the high half of PSR31, as defined by libbluray.
+const PROFILE_BITS: &[(u32, &str)] = &[
+    (
+        0x0001_0000,
+        "Bonus View: secondary video and audio, local storage",
+    ),
+    (0x0002_0000, "BD-Live: VFS and BD-J network access"),
+    (0x0008_0000, "audio-only player"),
+    (0x0010_0000, "stereoscopic 3D"),
+];
+
+/// A compare that checks PSR31 (player profile and version), e.g. to only offer BD-Live
+/// features on players that support them.
+#[derive(Clone, Copy, Debug)]
+pub struct ProfileCheck {
+    pub movie_object_index: u16,
+    pub navigation_command_index: u16,
+    /// The GPR holding a value derived from PSR31, if PSR31 isn't compared directly.
+    pub via_gpr: Option<u16>,
+    /// The immediate that PSR31, or the GPR derived from it, is compared against, if known.
+    pub compared: Option<u32>,
+}
+
+/// Describes a PSR31 value: the profile bits that are set and the player version, e.g.
+/// "BD-Live: VFS and BD-J network access; player version 2.00".
+pub fn describe_profile(value: u32) -> String {
+    let mut parts: Vec<String> = PROFILE_BITS
+        .iter()
+        .filter(|(bit, _)| value & bit != 0)
+        .map(|(_, name)| name.to_string())
+        .collect();
+    let unknown = value & 0xffff_0000 & !PROFILE_BITS.iter().fold(0, |bits, (bit, _)| bits | bit);
+    if unknown != 0 {
+        parts.push(format!("unknown profile bits {unknown:#x}"));
+    }
+    if value & 0xffff_0000 == 0 {
+        parts.push("profile 1".to_string());
+    }
+    let version = value & 0xffff;
+    parts.push(format!(
+        "player version {:x}.{:02x}",
+        version >> 8,
+        version & 0xff
+    ));
+    parts.join("; ")
+}
+
+impl MovieObjectFile {
+    /// Finds all compares that check PSR31, either directly or through a GPR that was loaded from
+    /// it earlier in the same movie object.
+    pub fn profile_checks(&self) -> Vec<ProfileCheck> {
+        let mut checks = vec![];
+        for (i, object) in (0..).zip(self.movie_objects.movie_objects.iter()) {
+            // GPRs currently holding a value derived from PSR31.
+            let mut derived: HashSet<u16> = HashSet::new();
+            for (j, command) in (0..).zip(object.navigation_commands.iter()) {
+                if let OperandCount::None = command.operand_count {
+                    continue;
+                }
+                match (command.command, command.destination) {
+                    (Command::Compare(_), _) => {
+                        if let OperandCount::DestinationOnly = command.operand_count {
+                            continue;
+                        }
+                        let sides = [
+                            (command.destination, command.source),
+                            (command.source, command.destination),
+                        ];
+                        for (operand, other) in sides {
+                            let via_gpr = match operand {
+                                Operand::Psr(31) => None,
+                                Operand::Gpr(gpr) if derived.contains(&gpr) => Some(gpr),
+                                _ => continue,
+                            };
+                            checks.push(ProfileCheck {
+                                movie_object_index: i,
+                                navigation_command_index: j,
+                                via_gpr,
+                                compared: other.as_u32(),
+                            });
+                        }
+                    }
+                    (Command::Set(Set::Move), Operand::Gpr(gpr)) => {
+                        if let OperandCount::DestinationAndSource = command.operand_count
+                            && matches!(command.source, Operand::Psr(31))
+                        {
+                            derived.insert(gpr);
+                        } else {
+                            derived.remove(&gpr);
+                        }
+                    }
+                    (Command::Set(Set::Swap | Set::Rnd), Operand::Gpr(gpr)) => {
+                        derived.remove(&gpr);
+                        if let Operand::Gpr(source) = command.source {
+                            derived.remove(&source);
+                        }
+                    }
+                    // Other arithmetic, e.g. masking with AND, keeps the value derived.
+                    _ => {}
+                }
+            }
+        }
+        checks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::describe_profile;
+    use crate::bluray::testing;
+
+    #[test]
+    fn profile_bits_and_version_are_described() {
+        assert_eq!(
+            describe_profile(0x0000_0100),
+            "profile 1; player version 1.00"
+        );
+        assert_eq!(
+            describe_profile(0x0003_0200),
+            "Bonus View: secondary video and audio, local storage; \
+             BD-Live: VFS and BD-J network access; player version 2.00"
+        );
+        assert_eq!(
+            describe_profile(0x0014_0300),
+            "stereoscopic 3D; unknown profile bits 0x40000; player version 3.00"
+        );
+    }
+
+    #[test]
+    fn checks_follow_gprs_loaded_from_psr31() {
+        let file = testing::file(&[&[
+            "MOV gpr1, psr31",
+            "AND gpr1, 0x20000",
+            "NE gpr1, 0x20000",
+            "MOV gpr1, 0",
+            "EQ gpr1, 0",
+            "LT 0x200, psr31",
+        ]]);
+        let checks: Vec<_> = file
+            .profile_checks()
+            .iter()
+            .map(|check| {
+                (
+                    check.navigation_command_index,
+                    check.via_gpr,
+                    check.compared,
+                )
+            })
+            .collect();
+        assert_eq!(
+            checks,
+            [(2, Some(1), Some(0x20000)), (5, None, Some(0x200))]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+/// The bitfields of a 12-byte navigation command, without any interpretation of their meaning.
+///
+/// Byte 0: operand count (3 bits), command group (2 bits), command sub-group (3 bits)
+/// Byte 1: destination is immediate (1 bit), source is immediate (1 bit), reserved (2 bits),
+///         branch option (4 bits)
+/// Byte 2: reserved (4 bits), compare option (4 bits)
+/// Byte 3: reserved (3 bits), set option (5 bits)
+/// Bytes 4..8: destination operand, big endian
+/// Bytes 8..12: source operand, big endian
+///
+/// Reserved bits are kept so that `to_bytes` reproduces the original bytes exactly.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RawCommand {
+    pub operand_count: u8,
+    pub command_group: u8,
+    pub command_sub_group: u8,
+    pub destination_is_immediate_value: bool,
+    pub source_is_immediate_value: bool,
+    pub branch_option: u8,
+    pub compare_option: u8,
+    pub set_option: u8,
+    pub destination: u32,
+    pub source: u32,
+    /// Bits 4-5 of byte 1, bits 4-7 of byte 2, and bits 5-7 of byte 3, in their original
+    /// positions.
+    pub reserved: [u8; 3],
+}
+
+impl RawCommand {
+    pub fn from_bytes(bytes: &[u8; 12]) -> Self {
+        Self {
+            operand_count: (bytes[0] >> 5) & 0x7,
+            command_group: (bytes[0] >> 3) & 0x3,
+            command_sub_group: bytes[0] & 0x7,
+            destination_is_immediate_value: (bytes[1] & (1 << 7)) != 0,
+            source_is_immediate_value: (bytes[1] & (1 << 6)) != 0,
+            branch_option: bytes[1] & 0xf,
+            compare_option: bytes[2] & 0xf,
+            set_option: bytes[3] & 0x1f,
+            destination: u32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+            source: u32::from_be_bytes(bytes[8..12].try_into().unwrap()),
+            reserved: [bytes[1] & 0x30, bytes[2] & 0xf0, bytes[3] & 0xe0],
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; 12] {
+        let mut bytes = [0; 12];
+        bytes[0] = ((self.operand_count & 0x7) << 5)
+            | ((self.command_group & 0x3) << 3)
+            | (self.command_sub_group & 0x7);
+        bytes[1] = (u8::from(self.destination_is_immediate_value) << 7)
+            | (u8::from(self.source_is_immediate_value) << 6)
+            | (self.reserved[0] & 0x30)
+            | (self.branch_option & 0xf);
+        bytes[2] = (self.reserved[1] & 0xf0) | (self.compare_option & 0xf);
+        bytes[3] = (self.reserved[2] & 0xe0) | (self.set_option & 0x1f);
+        bytes[4..8].copy_from_slice(&self.destination.to_be_bytes());
+        bytes[8..12].copy_from_slice(&self.source.to_be_bytes());
+        bytes
+    }
+
+    /// Whether any of the reserved bits are set.
+    pub fn has_reserved_bits(&self) -> bool {
+        self.reserved.iter().any(|&bits| bits != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RawCommand;
+
+    #[test]
+    fn every_header_byte_round_trips() {
+        for position in 0..4 {
+            for value in 0..=u8::MAX {
+                let mut bytes = [0x5a; 12];
+                bytes[position] = value;
+                assert_eq!(RawCommand::from_bytes(&bytes).to_bytes(), bytes);
+            }
+        }
+    }
+
+    #[test]
+    fn every_field_value_round_trips() {
+        let zero = RawCommand::from_bytes(&[0; 12]);
+        for operand_count in 0..8 {
+            for command_group in 0..4 {
+                for command_sub_group in 0..8 {
+                    let raw = RawCommand {
+                        operand_count,
+                        command_group,
+                        command_sub_group,
+                        ..zero
+                    };
+                    assert_eq!(RawCommand::from_bytes(&raw.to_bytes()), raw);
+                }
+            }
+        }
+        for branch_option in 0..16 {
+            for compare_option in 0..16 {
+                for set_option in 0..32 {
+                    let raw = RawCommand {
+                        branch_option,
+                        compare_option,
+                        set_option,
+                        ..zero
+                    };
+                    assert_eq!(RawCommand::from_bytes(&raw.to_bytes()), raw);
+                }
+            }
+        }
+        for destination_is_immediate_value in [false, true] {
+            for source_is_immediate_value in [false, true] {
+                let raw = RawCommand {
+                    destination_is_immediate_value,
+                    source_is_immediate_value,
+                    destination: 0x8000_0014,
+                    source: 0xffff_ffff,
+                    reserved: [0x30, 0xf0, 0xe0],
+                    ..zero
+                };
+                assert_eq!(RawCommand::from_bytes(&raw.to_bytes()), raw);
+            }
+        }
+    }
+
+    #[test]
+    fn fields_are_at_their_documented_bits() {
+        // EQ psr20, 2: two operands, compare group, immediate source, compare option 2.
+        let bytes = [
+            0x48, 0x40, 0x02, 0x00, 0x80, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x02,
+        ];
+        let raw = RawCommand::from_bytes(&bytes);
+        assert_eq!(
+            (raw.operand_count, raw.command_group, raw.command_sub_group),
+            (2, 1, 0)
+        );
+        assert!(!raw.destination_is_immediate_value && raw.source_is_immediate_value);
+        assert_eq!(
+            (raw.branch_option, raw.compare_option, raw.set_option),
+            (0, 2, 0)
+        );
+        assert_eq!((raw.destination, raw.source), (0x8000_0014, 2));
+        assert!(!raw.has_reserved_bits());
+    }
+}
@@ -0,0 +1,72 @@
+//! Helpers for building MovieObject.bdmv fixtures in tests.
+
+use super::{MovieObjectFile, NavigationCommand};
+use std::path::PathBuf;
+
+/// Assembles each movie object's navigation commands and lays them out as the bytes of a
+/// version 0200 MovieObject.bdmv without extension data.
+pub fn bytes(objects: &[&[&str]]) -> Vec<u8> {
+    let mut body = vec![];
+    body.extend_from_slice(&u16::try_from(objects.len()).unwrap().to_be_bytes());
+    for commands in objects {
+        // No flags.
+        body.extend_from_slice(&[0, 0]);
+        body.extend_from_slice(&u16::try_from(commands.len()).unwrap().to_be_bytes());
+        for command in *commands {
+            body.extend_from_slice(&command.parse::<NavigationCommand>().unwrap().to_bytes());
+        }
+    }
+    let mut bytes = b"MOBJ0200".to_vec();
+    bytes.resize(40, 0);
+    // The movie objects length counts the reserved bytes before the body.
+    bytes.extend_from_slice(&u32::try_from(4 + body.len()).unwrap().to_be_bytes());
+    bytes.extend_from_slice(&[0; 4]);
+    bytes.extend(body);
+    bytes
+}
+
+/// Parses the fixture built by [`bytes`].
+pub fn file(objects: &[&[&str]]) -> MovieObjectFile {
+    MovieObjectFile::from_bytes(&bytes(objects)).unwrap()
+}
+
+/// The navigation commands of a movie object, as assembly.
+pub fn assembly(file: &MovieObjectFile, movie_object_index: usize) -> Vec<String> {
+    file.movie_objects.movie_objects[movie_object_index]
+        .navigation_commands
+        .iter()
+        .map(NavigationCommand::to_string)
+        .collect()
+}
+
+/// Lays out the bytes of a version 0200 index.bdmv whose first playback, top menu, and titles
+/// each start the given HDMV movie object.
+pub fn index_bytes(first_playback: u16, top_menu: u16, titles: &[u16]) -> Vec<u8> {
+    let entry = |movie_object_index: u16| {
+        // An HDMV object, with the interactive playback type.
+        let mut entry = vec![0x40, 0, 0, 0, 0x40, 0];
+        entry.extend_from_slice(&movie_object_index.to_be_bytes());
+        entry.extend_from_slice(&[0; 4]);
+        entry
+    };
+    let mut indexes = entry(first_playback);
+    indexes.extend(entry(top_menu));
+    indexes.extend_from_slice(&u16::try_from(titles.len()).unwrap().to_be_bytes());
+    indexes.extend(titles.iter().copied().flat_map(entry));
+    let mut bytes = b"INDX0200".to_vec();
+    // The indexes follow the header and an empty AppInfoBDMV block of 34 bytes.
+    bytes.extend_from_slice(&78_u32.to_be_bytes());
+    bytes.resize(40, 0);
+    bytes.extend_from_slice(&34_u32.to_be_bytes());
+    bytes.resize(78, 0);
+    bytes.extend_from_slice(&u32::try_from(indexes.len()).unwrap().to_be_bytes());
+    bytes.extend(indexes);
+    bytes
+}
+
+pub fn scratch_directory(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("bd-region-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
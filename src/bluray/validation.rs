@@ -0,0 +1,142 @@
+use super::{Branch, Command, MovieObjectFile, Operand, OperandCount, Warning};
+
+/// The result of checking one structural invariant of a MovieObject.bdmv.
+#[derive(Debug)]
+pub struct InvariantCheck {
+    pub name: &'static str,
+    /// A description of each violation, with its location if it has one. Empty if the invariant
+    /// holds.
+    pub failures: Vec<String>,
+}
+
+impl MovieObjectFile {
+    /// Checks the structural invariants of the parsed file. Invariants that parsing already
+    /// enforces, e.g. a known header version and that every navigation command decodes, aren't
+    /// repeated.
+    pub fn validate(&self) -> Vec<InvariantCheck> {
+        let mut checks = vec![];
+
+        // The reserved bytes, then the count and movie objects.
+        let actual_len = 4 + self.serialize_body().len();
+        let declared_len = usize::try_from(self.movie_objects.byte_len).unwrap();
+        checks.push(InvariantCheck {
+            name: "declared movie objects length",
+            failures: if declared_len == actual_len {
+                vec![]
+            } else {
+                vec![format!(
+                    "declares {declared_len} bytes, but the movie objects take {actual_len}"
+                )]
+            },
+        });
+
+        let extension_data_start =
+            usize::try_from(u32::from_be_bytes(self.header[8..12].try_into().unwrap())).unwrap();
+        let expected_start = self.header.len() + 4 + actual_len;
+        checks.push(InvariantCheck {
+            name: "extension data start address",
+            failures: match (extension_data_start, self.extension_data.is_empty()) {
+                (0, true) => vec![],
+                (0, false) => vec![format!(
+                    "no start address, but {} bytes follow the movie objects",
+                    self.extension_data.len()
+                )],
+                (start, _) if start == expected_start => vec![],
+                (start, _) => vec![format!(
+                    "starts at {start:#x}, but the movie objects end at {expected_start:#x}"
+                )],
+            },
+        });
+
+        let warnings = self.warnings();
+        checks.push(InvariantCheck {
+            name: "no writes to read-only PSRs",
+            failures: warnings
+                .iter()
+                .filter(|warning| matches!(warning, Warning::ReadOnlyPsrWrite(..)))
+                .map(Warning::to_string)
+                .collect(),
+        });
+
+        checks.push(InvariantCheck {
+            name: "register operands in range",
+            failures: warnings
+                .iter()
+                .filter(|warning| {
+                    matches!(
+                        warning,
+                        Warning::GprOutOfRange(..) | Warning::PsrOutOfRange(..)
+                    )
+                })
+                .map(Warning::to_string)
+                .collect(),
+        });
+
+        let mut failures: Vec<String> = warnings
+            .iter()
+            .filter(|warning| matches!(warning, Warning::GoToOutOfRange(..)))
+            .map(Warning::to_string)
+            .collect();
+        let movie_objects_count = self.movie_objects.movie_objects.len();
+        for (i, object) in (0..).zip(self.movie_objects.movie_objects.iter()) {
+            for (j, command) in (0..).zip(object.navigation_commands.iter()) {
+                if let (
+                    Command::Branch(Branch::JumpObject | Branch::CallObject),
+                    OperandCount::DestinationOnly | OperandCount::DestinationAndSource,
+                    Operand::Immediate(target),
+                ) = (command.command, command.operand_count, command.destination)
+                    && usize::try_from(target).unwrap() >= movie_objects_count
+                {
+                    failures.push(format!(
+                        "movie object #{i} navigation command #{j} jumps to movie object #{target}, but there are only {movie_objects_count}"
+                    ));
+                }
+            }
+        }
+        checks.push(InvariantCheck {
+            name: "branch targets in range",
+            failures,
+        });
+
+        checks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bluray::{MovieObjectFile, testing};
+
+    #[test]
+    fn violations_are_reported_per_invariant() {
+        let mut bytes = testing::bytes(&[&["JUMP_OBJECT 3", "MOV psr20, 1"]]);
+        // Point the extension data start address past the end of the file.
+        bytes[8..12].copy_from_slice(&0x100_u32.to_be_bytes());
+        let file = MovieObjectFile::from_bytes(&bytes).unwrap();
+        let checks: Vec<_> = file
+            .validate()
+            .into_iter()
+            .map(|check| (check.name, check.failures))
+            .collect();
+        assert_eq!(checks[0], ("declared movie objects length", vec![]));
+        assert_eq!(
+            checks[1],
+            (
+                "extension data start address",
+                vec!["starts at 0x100, but the movie objects end at 0x4e".to_string()]
+            )
+        );
+        assert_eq!(checks[2].0, "no writes to read-only PSRs");
+        assert_eq!(checks[2].1.len(), 1);
+        assert_eq!(checks[3], ("register operands in range", vec![]));
+        assert_eq!(
+            checks[4],
+            (
+                "branch targets in range",
+                vec![
+                    "movie object #0 navigation command #0 jumps to movie object #3, but there are only 1"
+                        .to_string()
+                ]
+            )
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use super::{Branch, Command, MovieObjectFile, Operand, OperandCount, Set};
+use thiserror::Error;
+
+/// Something unusual about a MovieObject.bdmv that doesn't prevent it from being parsed.
+#[derive(Debug, Error)]
+pub enum Warning {
+    #[error("movie object #{0} navigation command #{1} writes to read-only PSR{2}")]
+    ReadOnlyPsrWrite(u16, u16, u8),
+    #[error("movie object #{0} navigation command #{1} has reserved bits set: {2:02x?}")]
+    ReservedBitsSet(u16, u16, [u8; 12]),
+    #[error(
+        "movie object #{0} navigation command #{1} jumps to navigation command #{2}, past the end of the movie object"
+    )]
+    GoToOutOfRange(u16, u16, u32),
+    #[error(
+        "movie object #{0} navigation command #{1} sets the immediate flag for an unused operand: {2:02x?}"
+    )]
+    UnusedOperandImmediate(u16, u16, [u8; 12]),
+    #[error("movie object #{0} navigation command #{1} uses a reserved opcode: {2:02x?}")]
+    ReservedCommand(u16, u16, [u8; 12]),
+    #[error(
+        "movie object #{0} navigation command #{1} is truncated; dropped the last {2} bytes of the file"
+    )]
+    TruncatedTail(u16, u16, usize),
+    #[error(
+        "movie object #{0} navigation command #{1} uses gpr{2}, but GPRs only go up to gpr4095"
+    )]
+    GprOutOfRange(u16, u16, u32),
+    #[error("movie object #{0} navigation command #{1} uses psr{2}, but PSRs only go up to psr127")]
+    PsrOutOfRange(u16, u16, u32),
+}
+
+fn is_read_only_psr(num: u8) -> bool {
+    matches!(num, 8 | 9 | 13 | 15..=20 | 29..=31 | 40 | 48..=61)
+}
+
+impl MovieObjectFile {
+    /// Collects warnings about anything unusual in the parsed file.
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = vec![];
+        if let Some((i, j, dropped)) = self.dropped_tail {
+            warnings.push(Warning::TruncatedTail(i, j, dropped));
+        }
+        for (i, object) in (0..).zip(self.movie_objects.movie_objects.iter()) {
+            for (j, command) in (0..).zip(object.navigation_commands.iter()) {
+                if let Command::Reserved { .. } = command.command {
+                    warnings.push(Warning::ReservedCommand(i, j, command.raw_bytes));
+                }
+                let raw = command.raw();
+                if raw.has_reserved_bits() {
+                    warnings.push(Warning::ReservedBitsSet(i, j, command.raw_bytes));
+                }
+                let (destination_used, source_used) = match command.operand_count {
+                    OperandCount::None => (false, false),
+                    OperandCount::DestinationOnly => (true, false),
+                    OperandCount::DestinationAndSource => (true, true),
+                };
+                if (raw.destination_is_immediate_value && !destination_used)
+                    || (raw.source_is_immediate_value && !source_used)
+                {
+                    warnings.push(Warning::UnusedOperandImmediate(i, j, command.raw_bytes));
+                }
+                // System set commands, e.g. SET_STREAM, pack fields into their operands, so an
+                // operand that isn't a valid register is expected there. Elsewhere, it's a
+                // register number that's out of range.
+                let is_system_set = raw.command_group == 2 && raw.command_sub_group == 1;
+                let operands = [
+                    (command.destination, raw.destination, destination_used),
+                    (command.source, raw.source, source_used),
+                ];
+                for (operand, value, used) in operands {
+                    if let Operand::Unknown(_) = operand
+                        && used
+                        && !is_system_set
+                        && !matches!(command.command, Command::Reserved { .. })
+                    {
+                        warnings.push(if value & 0x80000000 != 0 {
+                            Warning::PsrOutOfRange(i, j, value & !0x80000000)
+                        } else {
+                            Warning::GprOutOfRange(i, j, value)
+                        });
+                    }
+                }
+
+                if let (Command::Branch(Branch::GoTo), Operand::Immediate(target)) =
+                    (command.command, command.destination)
+                    && usize::try_from(target).unwrap() >= object.navigation_commands.len()
+                {
+                    warnings.push(Warning::GoToOutOfRange(i, j, target));
+                }
+
+                // Only the register-based set commands write to their operands; swap writes to
+                // both.
+                let written: &[Operand] = match (command.command, command.operand_count) {
+                    (Command::Set(Set::Swap), OperandCount::DestinationAndSource) => {
+                        &[command.destination, command.source]
+                    }
+                    (
+                        Command::Set(
+                            Set::Move
+                            | Set::Add
+                            | Set::Sub
+                            | Set::Mul
+                            | Set::Div
+                            | Set::Mod
+                            | Set::Rnd
+                            | Set::And
+                            | Set::Or
+                            | Set::Xor
+                            | Set::Bitset
+                            | Set::Bitclr
+                            | Set::ShiftLeft
+                            | Set::ShiftRight,
+                        ),
+                        OperandCount::DestinationOnly | OperandCount::DestinationAndSource,
+                    ) => &[command.destination],
+                    _ => &[],
+                };
+                for operand in written {
+                    if let &Operand::Psr(num) = operand
+                        && is_read_only_psr(num)
+                    {
+                        warnings.push(Warning::ReadOnlyPsrWrite(i, j, num));
+                    }
+                }
+            }
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Warning;
+    use crate::bluray::{MovieObjectFile, OpenError, testing};
+
+    #[test]
+    fn strict_rejects_only_files_with_warnings() {
+        let file = testing::file(&[&["MOV gpr1, psr20", "MOV psr20, 2"]]);
+        assert!(matches!(
+            file.warnings()[..],
+            [Warning::ReadOnlyPsrWrite(0, 1, 20)]
+        ));
+        assert!(matches!(
+            file.strict(),
+            Err(OpenError::Strict(Warning::ReadOnlyPsrWrite(0, 1, 20)))
+        ));
+        let file = testing::file(&[&["MOV gpr1, psr20", "EQ gpr1, 2"]]);
+        assert!(file.warnings().is_empty());
+        assert!(file.strict().is_ok());
+    }
+
+    #[test]
+    fn stray_immediate_flags_are_reported() {
+        let mut bytes = testing::bytes(&[&["NOP", "MOV gpr1, 2", "JUMP_OBJECT 1"]]);
+        // Flag the destination of the NOP, at byte 54, as an immediate.
+        bytes[55] |= 0x80;
+        let file = MovieObjectFile::from_bytes(&bytes).unwrap();
+        let raw_bytes: [u8; 12] = bytes[54..66].try_into().unwrap();
+        assert!(matches!(
+            file.warnings()[..],
+            [Warning::UnusedOperandImmediate(0, 0, bytes)] if bytes == raw_bytes
+        ));
+    }
+
+    #[test]
+    fn out_of_range_registers_are_reported() {
+        let file = testing::file(&[&[
+            "MOV gpr1, raw(0x1388)",
+            "ADD raw(0x800000c8), 1",
+            // Packed fields aren't register numbers.
+            "SET_STREAM raw(0x80024000), raw(0x8001)",
+        ]]);
+        assert!(matches!(
+            file.warnings()[..],
+            [
+                Warning::GprOutOfRange(0, 0, 5000),
+                Warning::PsrOutOfRange(0, 1, 200)
+            ]
+        ));
+        assert_eq!(
+            file.warnings()[0].to_string(),
+            "movie object #0 navigation command #0 uses gpr5000, but GPRs only go up to gpr4095"
+        );
+    }
+
+    #[test]
+    fn goto_past_the_end_is_reported() {
+        // A GoTo to the last command is fine, but one past it isn't.
+        let file = testing::file(&[&["GOTO 2", "GOTO 3", "NOP"], &["GOTO 0"]]);
+        assert!(matches!(
+            file.warnings()[..],
+            [Warning::GoToOutOfRange(0, 1, 3)]
+        ));
+        assert_eq!(
+            file.warnings()[0].to_string(),
+            "movie object #0 navigation command #1 jumps to navigation command #3, past the end of the movie object"
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Decoding of hex dumps, e.g. pasted into an issue.
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum HexError {
+    #[error("line {0}: odd number of hex digits in {1:?}")]
+    OddLength(usize, String),
+    #[error("line {0}: invalid hex digits {1:?}")]
+    InvalidDigits(usize, String),
+}
+
+/// Decodes whitespace- or colon-separated hex bytes. Runs of digits without separators, e.g.
+/// "4d4f424a", are also accepted. Lines starting with '#' are comments.
+pub fn decode(text: &str) -> Result<Vec<u8>, HexError> {
+    let mut bytes = vec![];
+    for (line_number, line) in (1..).zip(text.lines()) {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        for token in line.split(|c: char| c.is_whitespace() || c == ':') {
+            if token.len() % 2 != 0 {
+                return Err(HexError::OddLength(line_number, token.to_string()));
+            }
+            for pair in token.as_bytes().chunks_exact(2) {
+                let pair = std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or_else(|| HexError::InvalidDigits(line_number, token.to_string()))?;
+                bytes.push(pair);
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+/// Encodes bytes as lowercase hex digits without separators, which `decode` accepts.
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HexError, decode, encode};
+    use crate::bluray::{MovieObjectFile, testing};
+
+    #[test]
+    fn movie_object_file_round_trips() {
+        let bytes = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "JUMP_OBJECT 1"]]);
+        assert_eq!(decode(&encode(&bytes)).unwrap(), bytes);
+        // The same file as a commented dump with a few bytes per line, in mixed case and with
+        // both kinds of separator.
+        let dump: String = std::iter::once("# MovieObject.bdmv\n".to_string())
+            .chain(bytes.chunks(8).enumerate().map(|(n, line)| {
+                let line = line
+                    .iter()
+                    .map(|byte| format!("{byte:02X}"))
+                    .collect::<Vec<_>>();
+                let separator = if n % 2 == 0 { " " } else { ":" };
+                format!("{}\n", line.join(separator))
+            }))
+            .collect();
+        let decoded = decode(&dump).unwrap();
+        assert_eq!(decoded, bytes);
+        assert_eq!(
+            MovieObjectFile::from_bytes(&decoded).unwrap().serialize(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert!(matches!(
+            decode("4d 4f\n42 4a 3"),
+            Err(HexError::OddLength(2, token)) if token == "3"
+        ));
+        assert!(matches!(
+            decode("# comment\n4d4g"),
+            Err(HexError::InvalidDigits(2, token)) if token == "4d4g"
+        ));
+    }
+}
@@ -1,23 +1,45 @@
+mod base64;
 mod bluray;
+mod hex;
+mod sha256;
 
-use clap::{Args, Parser, Subcommand};
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use dialoguer::Confirm;
-use std::collections::HashSet;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use thiserror::Error;
 
 use crate::bluray::{
-    MovieObject, MovieObjectFile, NavigationCommand, Operand, OperandCount, Region,
+    Access, AssembleError, Branch, Compare, ComputedGate, DetectionTrace, DiscModel, EditError,
+    IndexError, IndexFile, MovieObjectFile, MovieObjectModel, NavigationCommand, Operand,
+    OperandCount, PatchAction, PatchError, ProfileCheck, Region, RegionFinding, RegionFindingKind,
+    Set, Version, Warning,
 };
 
 #[derive(Parser)]
+#[command(
+    after_help = "Exit status: 0 on success; for test, 1 if the disc is region locked, 2 if \
+it is country locked, or 3 if both; for validate, 1 if any invariant is violated; 64 on any error, e.g. if the disc can't be read or parsed."
+)]
 /// Utility to test or remove region checks from Blu-Ray disc. Blu-Ray discs can perform region
 /// checks in MovieObject.bdmv or in BD-J; this utility only handles the former.
 struct Cli {
     /// Path to the disc, i.e. the directory that contains the top-level BDMV and CERTIFICATE
-    /// directories.
+    /// directories, or directly to a MovieObject.bdmv file.
     path: PathBuf,
+    /// Refuse to handle a disc with anything unusual in MovieObject.bdmv, e.g. writes to read-only
+    /// PSRs or reserved bits that are set.
+    #[arg(long)]
+    strict: bool,
+    /// If MovieObject.bdmv ends partway through a navigation command, e.g. because it was copied
+    /// from a damaged disc, work with the commands before it instead of failing. Anything written
+    /// only includes those commands.
+    #[arg(long, conflicts_with = "strict")]
+    recover: bool,
     #[command(subcommand)]
     command: Command,
 }
@@ -25,31 +47,333 @@ struct Cli {
 #[derive(Subcommand)]
 enum Command {
     /// For debugging.
-    Dump,
+    Dump(DumpArgs),
     /// Test if a disc is region or country locked.
-    Test,
+    Test(TestArgs),
+    /// List the region and country values a disc compares against.
+    Expectations,
     /// Remove region checks from a disc.
-    Remove(RemoveArgs),
+    Remove(Box<RemoveArgs>),
+    /// Like dump, but the path is a hex dump of MovieObject.bdmv, e.g. pasted into an issue.
+    /// Bytes may be separated by whitespace or colons, and lines starting with '#' are ignored.
+    FromHex(DumpArgs),
+    /// Like dump, but the path is MovieObject.bdmv encoded as base64, e.g. as written by
+    /// remove --base64. Whitespace and line breaks are ignored.
+    FromBase64(DumpArgs),
+    /// Write a minimal MovieObject.bdmv to the path, e.g. as a known-good baseline for testing
+    /// players or building patches from scratch.
+    Template(TemplateArgs),
+    /// Check that re-serializing MovieObject.bdmv reproduces it byte for byte, e.g. as a smoke
+    /// test over a collection of discs. Fails with the first differing offset otherwise.
+    Reencode,
+    /// Only print the number of movie objects. This reads just the start of MovieObject.bdmv,
+    /// so it's fast across a large collection of discs.
+    Count,
+    /// Print each region or country gate as a standalone snippet, e.g. for an issue report: the
+    /// commands that load the compared GPR, the compare, and the guarded command, as assembly
+    /// and hex.
+    ExtractGate,
+    /// Check MovieObject.bdmv against the structural invariants of the format, e.g. that the
+    /// declared lengths match and branch targets exist, and list each violation. Fails to parse
+    /// at all if the version is unknown or a navigation command doesn't decode.
+    Validate,
+    /// Print a SHA-256 fingerprint of the navigation logic, e.g. to find the same title across
+    /// different rips in a large collection. Only the movie objects count and movie objects are
+    /// hashed, after canonicalizing them: reserved bytes, extension data, and how nops are encoded
+    /// don't affect it.
+    Fingerprint,
+    /// Print everything known about a single navigation command, e.g. for an issue report: the
+    /// decoded fields, the raw bytes, the file offset, and the assembly with PSR roles.
+    #[command(name = "dump-command")]
+    DumpOne {
+        /// The 0-based movie object index, a comma, and the 0-based navigation command index,
+        /// e.g. "0,3".
+        locator: NavigationCommandLocator,
+    },
 }
 
 #[derive(Args)]
-struct RemoveArgs {
-    /// What region to overwrite use of PSR 20 with.
+struct TemplateArgs {
+    /// How many movie objects to generate.
+    #[arg(long, default_value_t = 1)]
+    objects: u16,
+    /// How many nops to generate in each movie object.
+    #[arg(long, default_value_t = 1)]
+    commands_per_object: u16,
+}
+
+#[derive(Args)]
+struct DumpArgs {
+    /// Print a summary of the file (path, size, version, and counts) before the movie objects.
     #[arg(long)]
-    region: Region,
+    info: bool,
+    /// Print a header for each movie object, followed by its navigation commands.
+    #[arg(long)]
+    grouped: bool,
+    /// How to print navigation commands.
+    #[arg(long, value_enum, default_value_t = DumpFormat::Debug)]
+    format: DumpFormat,
+    /// Only print movie objects starting at this 0-based index.
+    #[arg(long)]
+    since_object: Option<u16>,
+    /// Only print movie objects up to and including this 0-based index.
+    #[arg(long)]
+    until_object: Option<u16>,
+    /// Also print the reserved and header bytes that are otherwise ignored: the extension data
+    /// start address, the reserved header bytes, the reserved bytes before the movie objects
+    /// count, and each movie object's flags.
+    #[arg(long)]
+    raw_header: bool,
+    /// Print the byte range of each section of the file, e.g. the header fields, the movie
+    /// objects, and the extension data, along with the total size.
+    #[arg(long)]
+    layout: bool,
+    /// Print any warnings about the parsed file, and exit with a nonzero status if there are any.
+    /// Unlike --strict, the file is still dumped.
+    #[arg(long)]
+    fail_on_warning: bool,
+    /// Print the file offset of each movie object and navigation command, e.g. to find them in
+    /// a hex editor.
+    #[arg(long)]
+    offsets: bool,
+    /// After the movie objects, list the navigation commands that use each GPR, and whether they
+    /// read it, write it, or both.
+    #[arg(long)]
+    gpr_xref: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum DumpFormat {
+    /// The decoded fields and raw bytes of each command.
+    Debug,
+    /// Assembly, e.g. "MOV gpr1, psr20".
+    Asm,
+    /// The textual convention of the lw/BluRay wiki, with the raw bytes as hex words.
+    Wiki,
+    /// A deterministic, fixed-width layout for diffing disassembly across disc versions, e.g. in
+    /// version control: one line per movie object and per command, with the raw bytes and the
+    /// assembly, and nothing that depends on the path or on how the tool was invoked. Offsets
+    /// are only included with --offsets. Ignores --info, --grouped, --raw-header, and --layout.
+    Stable,
+}
+
+#[derive(Args)]
+struct TestArgs {
+    /// Only print the number of region and country checks: the same checks that the listing
+    /// shows and the exit code reflects, including compares of GPRs loaded from PSR19 or PSR20.
+    #[arg(long)]
+    count_only: bool,
+    /// How to print the findings.
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    report_format: ReportFormat,
+    /// Describe each check in plain English.
+    #[arg(long)]
+    explain: bool,
+    /// Only report region (PSR20) checks.
+    #[arg(long, conflicts_with = "country_only")]
+    region_only: bool,
+    /// Only report country (PSR19) checks.
+    #[arg(long)]
+    country_only: bool,
+    /// Also test BDMV/BACKUP/MovieObject.bdmv, and warn if it differs from the primary file. A
+    /// stale backup can re-lock a disc that was only patched in one place.
+    #[arg(long, conflicts_with_all = ["count_only", "report_format"])]
+    check_backup: bool,
+    /// Only print checks that are repeated in several movie objects, grouped by what they compare
+    /// and whether they branch to a "wrong region" screen. Each of them needs patching.
+    #[arg(long, conflicts_with_all = ["count_only", "report_format", "explain"])]
+    group_duplicates: bool,
+    /// Also print this many navigation commands before and after each check, within the same
+    /// movie object.
+    #[arg(long, default_value_t = 0)]
+    context: u16,
+    /// Keep running, and clear the screen and report again whenever MovieObject.bdmv changes,
+    /// e.g. while editing it with a hex editor. The file is polled, and a file that can't be
+    /// parsed, e.g. because it's being written, is read again a few times, then on its next
+    /// change.
+    #[arg(long, conflicts_with = "check_backup")]
+    watch: bool,
+    /// Also report compares against an immediate that looks like a country code, e.g. "JP", even
+    /// if the other side isn't known to come from PSR19. This catches country checks that load
+    /// the GPR in an unusual order, but may report some compares that aren't country checks.
+    #[arg(long, conflicts_with = "region_only")]
+    ascii_country_compare: bool,
+    /// Instead of the findings, print for every navigation command whether detection flagged it
+    /// as a region or country check, and why, e.g. to understand a missed check.
+    #[arg(long, conflicts_with_all = ["count_only", "report_format", "explain", "group_duplicates"])]
+    trace_detection: bool,
+    /// Report each check by the titles that reach it, e.g. "region check reachable from Title
+    /// 1", rather than by its movie object. Titles are read from index.bdmv next to
+    /// MovieObject.bdmv and followed through jumps and calls to other movie objects. A check
+    /// that no title reaches, or any check if index.bdmv can't be read, is reported by its
+    /// movie object instead.
+    #[arg(long, conflicts_with_all = ["count_only", "report_format", "explain", "group_duplicates", "trace_detection", "check_backup", "watch"])]
+    by_title: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ReportFormat {
+    Text,
+    /// Comma-separated values with a header row, e.g. for spreadsheets.
+    Csv,
+}
+
+#[derive(Args, Clone)]
+struct RemoveArgs {
+    /// How to neutralize region and country checks.
+    #[arg(long, value_enum, default_value_t = Mode::Rewrite)]
+    mode: Mode,
+    /// What region to overwrite use of PSR 20 with. Required unless --preset or --reference is
+    /// given. Not allowed with --mode=force-pass.
+    #[arg(long, ignore_case = true)]
+    region: Option<Region>,
     /// What country to overwrite use of PSR 19 with. This should be an ISO 3166-1 alpha-2 code
-    /// specified in uppercase letters, e.g. "US" or "JP".
+    /// specified in uppercase letters, e.g. "US" or "JP". Required unless --preset or
+    /// --reference is given. Not allowed with --mode=force-pass.
     #[arg(long, value_parser=parse_country)]
-    country: String,
+    country: Option<String>,
+    /// A country and region to use together, written as the country, a dash, and the region,
+    /// e.g. "US-A", "JP-A", or "UK-B" ("UK" is accepted for "GB"). --region and --country
+    /// override the corresponding part.
+    #[arg(long, value_parser=parse_preset)]
+    preset: Option<Preset>,
+    /// Another disc, or its MovieObject.bdmv, whose region and country checks expect the values
+    /// to patch in, e.g. an original disc from the desired region. Fails if its region checks
+    /// don't agree on a single region. --region and --country override the corresponding part.
+    #[arg(long, conflicts_with = "preset")]
+    reference: Option<PathBuf>,
+    /// Reject a country that isn't an assigned ISO 3166-1 alpha-2 code, e.g. "UK" instead of
+    /// "GB". Regions are always limited to the known values. Use --force to only warn instead.
+    #[arg(long)]
+    validate_values: bool,
+    /// With --validate-values, only warn about an unrecognized country.
+    #[arg(long, requires = "validate_values")]
+    force: bool,
     /// Any additional navigation commands to patch out with a nop. A location consists of a
-    /// 0-based movie object index, a comma, and a 0-based navigation command index.
+    /// 0-based movie object index, a comma, and a 0-based navigation command index, or "*" for
+    /// every navigation command in the movie object.
+    #[arg(long)]
+    nop_patch: Vec<NopPatch>,
+    /// Navigation commands to insert, written as a location, an equals sign, and the command in
+    /// assembly, e.g. "3,0=MOV gpr1, 2". The command is inserted before the navigation command
+    /// currently at that location; use an index one past the last command to append. Locations
+    /// refer to the original command indices, before any insertions.
+    #[arg(long)]
+    insert: Vec<Insertion>,
+    /// Navigation commands to delete, written as locations like for --nop-patch. GoTo targets
+    /// after a deleted command are shifted to keep pointing at the same commands.
+    #[arg(long, conflicts_with = "insert")]
+    delete: Vec<NavigationCommandLocator>,
+    /// MovieObject.bdmv version to write in the output header. By default, the input version is
+    /// preserved.
     #[arg(long)]
-    nop_patch: Vec<NavigationCommandLocator>,
+    assume_version: Option<Version>,
+    /// Before writing, re-parse the patched file and fail if it can't be parsed or if any region
+    /// or country checks remain. Requires --mode=rewrite, since force-pass leaves the reads of
+    /// PSR19 and PSR20 in place.
+    #[arg(long)]
+    verify_roundtrip: bool,
+    /// Change as few bytes as possible: only the PSR operand and its immediate flag of each
+    /// region or country check are rewritten, and nothing is inserted, removed, or replaced.
+    /// Prints the number of bytes changed.
+    #[arg(long, conflicts_with_all = ["nop_patch", "insert", "delete", "assume_version", "canonicalize_nops", "collapse_nops"])]
+    minimal_diff: bool,
+    /// Replace nops that have nonzero operands or other stray bits with all zero bytes, e.g. to
+    /// make diffs against other patched files cleaner. Prints the number of nops changed.
+    #[arg(long)]
+    canonicalize_nops: bool,
+    /// After patching, delete nops that directly follow another nop, e.g. to slim down runs left
+    /// by nop-patching. GoTo targets are shifted like for --delete. Prints the number of nops
+    /// deleted.
+    #[arg(long)]
+    collapse_nops: bool,
+    /// Refuse to write anything if the patch would change more than this many bytes of the
+    /// input, e.g. because a nop patch covered more than intended. Inserted commands count as
+    /// changing every byte after them.
+    #[arg(long)]
+    max_changes: Option<usize>,
+    /// Fail without writing anything if no region, country, or nop patch would change a
+    /// navigation command, e.g. so a pipeline that expects every disc to need patching notices
+    /// a clean one.
+    #[arg(long)]
+    require_changes: bool,
+    /// After forcing checks to pass, run detection again on the patched output and force any
+    /// gate it leads to that is still in place, e.g. a second compare of a copied region value
+    /// that the first gate hid. Prints the number of passes and how many gates remain. Requires
+    /// --mode=force-pass: rewrite mode already rewrites every read of PSR19 and PSR20 in one
+    /// pass.
+    #[arg(long)]
+    two_pass: bool,
+    /// What to do with reserved bytes in the header and reserved bits in navigation commands.
+    #[arg(long, value_enum, default_value_t = ReservedPolicy::Preserve)]
+    reserved_policy: ReservedPolicy,
+    /// Clear the immediate flags of operands that a navigation command's operand count says are
+    /// unused, which some authoring tools set and which can confuse decoders and players.
+    #[arg(long, conflicts_with = "minimal_diff")]
+    normalize_immediate_flags: bool,
+    /// Only write the movie objects block, e.g. to splice into another file with other tools: the
+    /// 2-byte movie objects count, then for each movie object its 2-byte flags, its 2-byte
+    /// navigation commands count, and its 12-byte navigation commands, all big endian. The
+    /// header, movie objects length, reserved bytes, and extension data are left out.
+    #[arg(long, conflicts_with = "in_place")]
+    body_only: bool,
+    /// Also write a list of the files written, including any backup or BDMV directory, to this
+    /// path. Each line holds a file's SHA-256, its size in bytes, and its path, separated by two
+    /// spaces.
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+    /// Also assemble a BDMV directory here: the patched MovieObject.bdmv, in both the directory
+    /// and its BACKUP directory so the backup can't re-lock the disc, along with unchanged copies
+    /// of index.bdmv and its backup from the disc.
+    #[arg(long, conflicts_with = "body_only")]
+    bdmv_dir: Option<PathBuf>,
+    /// Write the output as base64 text instead of binary, e.g. to embed it in config. Read it
+    /// back with from-base64.
+    #[arg(long, conflicts_with_all = ["in_place", "bdmv_dir"])]
+    base64: bool,
+    /// Set the modification time of the output to that of the input, e.g. so patched discs keep
+    /// their place in listings sorted by time.
+    #[arg(long)]
+    preserve_mtime: bool,
+    /// Don't flush the output and its directory to disk around renaming it into place. This is
+    /// faster, but a power loss right after patching may leave an empty or partial file, even
+    /// with --in-place.
+    #[arg(long)]
+    no_fsync: bool,
+    /// Patch every disc listed in this file, one path per line, with the same settings. The
+    /// listed paths are relative to the disc path, which is the root of the library, and each
+    /// patched MovieObject.bdmv is written to the same relative path under the output path.
+    /// Failures are reported at the end without stopping the rest of the list. Blank lines and
+    /// lines starting with '#' are ignored.
+    #[arg(long, conflicts_with_all = ["in_place", "bdmv_dir", "manifest"])]
+    from_list: Option<PathBuf>,
     #[command(flatten)]
     output: Output,
 }
 
-#[derive(Args)]
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Mode {
+    /// Make region and country checks read the given values instead of PSR20 and PSR19.
+    Rewrite,
+    /// Instead of rewriting region and country values, patch the branch of each region or
+    /// country compare so that the check always passes: the compare becomes a nop if the
+    /// guarded command, e.g. a GoTo, leads to the passing path, or a GoTo past the guarded
+    /// command if that leads to a "wrong region" screen. Which way passes is worked out from
+    /// where each path leads, then from the compare condition; gates where neither tells are
+    /// reported and left alone.
+    ForcePass,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ReservedPolicy {
+    /// Keep reserved bytes and bits as they are in the input, so unpatched parts of the file
+    /// round-trip byte for byte.
+    Preserve,
+    /// Clear reserved bytes and bits, for a canonical file. Prints the number of bytes changed.
+    Zero,
+}
+
+#[derive(Args, Clone)]
 #[group(required = true, multiple = false)]
 struct Output {
     /// Where to save the new MovieObject.bdmv file.
@@ -66,6 +390,24 @@ fn parse_country(s: &str) -> Result<String, String> {
     }
 }
 
+#[derive(Clone)]
+struct Preset {
+    country: String,
+    region: Region,
+}
+
+fn parse_preset(s: &str) -> Result<Preset, String> {
+    let (country, region) = s
+        .split_once('-')
+        .ok_or_else(|| "preset must be a country and a region, e.g. 'US-A'".to_string())?;
+    let country = match country.to_ascii_uppercase().as_str() {
+        "UK" => "GB".to_string(),
+        country => parse_country(country)?,
+    };
+    let region = Region::from_str(region, true)?;
+    Ok(Preset { country, region })
+}
+
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
 struct NavigationCommandLocator {
     movie_object_index: u16,
@@ -97,126 +439,1778 @@ impl std::str::FromStr for NavigationCommandLocator {
     }
 }
 
-fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
-    let file = MovieObjectFile::open(&cli.path)?;
+#[derive(Clone, Copy)]
+enum NopPatch {
+    NavigationCommand(NavigationCommandLocator),
+    /// Every navigation command in the movie object.
+    MovieObject(u16),
+}
+
+impl std::str::FromStr for NopPatch {
+    type Err = NavigationCommandLocatorParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_suffix(",*") {
+            Some(movie_object_index) => Ok(NopPatch::MovieObject(
+                movie_object_index
+                    .parse()
+                    .map_err(Self::Err::InvalidMovieObjectIndex)?,
+            )),
+            None => Ok(NopPatch::NavigationCommand(s.parse()?)),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Insertion {
+    locator: NavigationCommandLocator,
+    command: NavigationCommand,
+}
+
+#[derive(Debug, Error)]
+enum InsertionParseError {
+    #[error("missing equals sign")]
+    MissingEquals,
+    #[error("invalid location")]
+    InvalidLocator(#[source] NavigationCommandLocatorParseError),
+    #[error("invalid navigation command")]
+    InvalidCommand(#[source] AssembleError),
+}
+
+impl std::str::FromStr for Insertion {
+    type Err = InsertionParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (locator, command) = s.split_once('=').ok_or(Self::Err::MissingEquals)?;
+        Ok(Insertion {
+            locator: locator.parse().map_err(Self::Err::InvalidLocator)?,
+            command: command.parse().map_err(Self::Err::InvalidCommand)?,
+        })
+    }
+}
+
+/// Exit code bit for `test` finding region checks.
+const EXIT_REGION_LOCKED: u8 = 1;
+/// Exit code bit for `test` finding country checks.
+const EXIT_COUNTRY_LOCKED: u8 = 2;
+/// Exit code for `validate` finding a violated invariant.
+const EXIT_INVALID: u8 = 1;
+/// Exit code for any error, e.g. if the disc can't be read or parsed.
+const EXIT_ERROR: u8 = 64;
+
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(err) => {
+            let _ = err.print();
+            return if err.use_stderr() {
+                ExitCode::from(EXIT_ERROR)
+            } else {
+                ExitCode::SUCCESS
+            };
+        }
+    };
+    ExitCode::from(exit_status(run(cli)))
+}
+
+/// Maps the result of a command to the exit status documented in `--help`, reporting any error.
+fn exit_status(result: anyhow::Result<u8>) -> u8 {
+    match result {
+        Ok(status) => status,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            EXIT_ERROR
+        }
+    }
+}
+
+fn run(cli: Cli) -> anyhow::Result<u8> {
+    // The path is the output here, so there's nothing to open.
+    if let Command::Template(args) = cli.command {
+        args.exec(&cli.path)?;
+        return Ok(0);
+    }
+    // The path is the root of a library, not a disc.
+    if let Command::Remove(args) = &cli.command
+        && args.from_list.is_some()
+    {
+        return args.clone().exec_batch(&cli.path, cli.strict);
+    }
+    let path = bluray::resolve_path(&cli.path)?;
+    if cli.path.is_dir() && path != cli.path.join("BDMV").join("MovieObject.bdmv") {
+        eprintln!("using {}", path.display());
+    }
+    if let Command::Count = cli.command {
+        println!("{}", MovieObjectFile::read_movie_objects_count(&path)?);
+        return Ok(0);
+    }
+    let parse = if cli.recover {
+        MovieObjectFile::from_bytes_recovering
+    } else {
+        MovieObjectFile::from_bytes
+    };
+    let file = match cli.command {
+        Command::FromHex(_) => {
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            parse(&hex::decode(&text)?)?
+        }
+        Command::FromBase64(_) => {
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            parse(&base64::decode(&text)?)?
+        }
+        _ if cli.recover => MovieObjectFile::open_recovering(&path)?,
+        _ => MovieObjectFile::open(&path)?,
+    };
+    if cli.recover {
+        for warning in file.warnings() {
+            if let Warning::TruncatedTail(..) = warning {
+                eprintln!("warning: {warning}");
+            }
+        }
+    }
+    let file = if cli.strict { file.strict()? } else { file };
 
     match cli.command {
-        Command::Dump => dump(file),
-        Command::Test => test(file),
+        Command::Dump(args) | Command::FromHex(args) | Command::FromBase64(args) => {
+            args.exec(&path, file, &mut std::io::stdout().lock())?
+        }
+        Command::Test(args) => return args.exec(&path, file),
+        Command::Expectations => expectations(file),
+        Command::ExtractGate => extract_gates(&mut std::io::stdout().lock(), &file)?,
+        Command::Validate => return Ok(validate(&file)),
+        Command::Fingerprint => println!("{}", fingerprint(file)),
+        Command::DumpOne { locator } => {
+            dump_command(&mut std::io::stdout().lock(), &file, locator)?
+        }
         // TODO: Plumbing the original path through like this is a bit odd.
-        Command::Remove(args) => args.exec(&cli.path, file)?,
+        Command::Remove(args) => args.exec(&path, file, &mut std::io::stdout().lock())?,
+        Command::Reencode => reencode(&mut std::io::stdout().lock(), &path, &file)?,
+        Command::Template(_) | Command::Count => unreachable!(),
     };
+    Ok(0)
+}
+
+/// Checks that serializing `file` reproduces the file at `path` byte for byte.
+fn reencode(out: &mut impl Write, path: &Path, file: &MovieObjectFile) -> anyhow::Result<()> {
+    // With --recover, parsing doesn't check the round trip itself, so compare against what's on
+    // disk.
+    let original =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let serialized = file.serialize();
+    if let Some(offset) = first_difference(&original, &serialized) {
+        anyhow::bail!(
+            "{}: re-encoding differs from the original at offset {offset:#x}",
+            path.display()
+        );
+    }
+    writeln!(
+        out,
+        "{}: re-encoded {} bytes identically",
+        path.display(),
+        serialized.len()
+    )?;
     Ok(())
 }
 
-fn dump(file: MovieObjectFile) {
-    println!("movie object header: {:02x?}", file.header);
-    println!("movie objects byte size: {}", file.movie_objects.byte_len);
-    for (i, object) in (0..).zip(file.movie_objects.movie_objects.iter()) {
-        for (j, command) in (0..).zip(object.navigation_commands.iter()) {
-            println!("movie object #{i} navigation command #{j} {command:?}");
+/// Returns the offset of the first byte that differs between `a` and `b`, counting the end of
+/// the shorter one as a difference.
+fn first_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+    a.iter()
+        .zip(b)
+        .position(|(a, b)| a != b)
+        .or_else(|| (a.len() != b.len()).then(|| a.len().min(b.len())))
+}
+
+impl DumpArgs {
+    fn exec(self, path: &Path, file: MovieObjectFile, out: &mut impl Write) -> anyhow::Result<()> {
+        let count = file.movie_objects.movie_objects.len();
+        let since = self.since_object.unwrap_or(0);
+        let until = self
+            .until_object
+            .map_or(count.saturating_sub(1), usize::from);
+        for index in [self.since_object, self.until_object].into_iter().flatten() {
+            anyhow::ensure!(
+                usize::from(index) < count,
+                "movie object #{index} out of range; there are {count} movie objects"
+            );
         }
+        anyhow::ensure!(
+            usize::from(since) <= until,
+            "--since-object must not be greater than --until-object"
+        );
+
+        let model = file.model();
+        if self.format == DumpFormat::Stable {
+            print_stable(out, &file, &model, since, until, self.offsets)?;
+        } else {
+            if self.info {
+                for line in summarize(path, &model)? {
+                    writeln!(out, "{line}")?;
+                }
+            }
+            if self.layout {
+                for section in file.layout() {
+                    writeln!(
+                        out,
+                        "{:#06x}..{:#06x} ({} bytes): {}",
+                        section.range.start,
+                        section.range.end,
+                        section.range.len(),
+                        section.name
+                    )?;
+                }
+                writeln!(out, "total: {} bytes", file.serialize().len())?;
+            }
+            writeln!(out, "movie object header: {:02x?}", file.header)?;
+            writeln!(
+                out,
+                "movie objects byte size: {}",
+                file.movie_objects.byte_len
+            )?;
+            if self.raw_header {
+                writeln!(
+                    out,
+                    "extension data start address: {:02x?}",
+                    &file.header[8..12]
+                )?;
+                writeln!(out, "header reserved bytes: {:02x?}", &file.header[12..40])?;
+                writeln!(
+                    out,
+                    "movie objects reserved bytes: {:02x?}",
+                    file.movie_objects.reserved
+                )?;
+            }
+            for object in &model.movie_objects {
+                let i = object.index;
+                if i < since || usize::from(i) > until {
+                    continue;
+                }
+                if self.grouped {
+                    match object.file_offset.filter(|_| self.offsets) {
+                        Some(offset) => writeln!(
+                            out,
+                            "movie object #{i} at {offset:#x} (flags {}):",
+                            describe_flags(object)
+                        )?,
+                        None => {
+                            writeln!(out, "movie object #{i} (flags {}):", describe_flags(object))?
+                        }
+                    }
+                } else if self.raw_header {
+                    writeln!(
+                        out,
+                        "movie object #{i} flags: {:02x?}",
+                        object.flags.to_be_bytes()
+                    )?;
+                }
+                if object.navigation_commands.is_empty() {
+                    if self.grouped {
+                        writeln!(out, "    (no commands)")?;
+                    } else {
+                        writeln!(out, "movie object #{i}: (no commands)")?;
+                    }
+                }
+                for command in &object.navigation_commands {
+                    let j = command.index;
+                    let description = match self.format {
+                        DumpFormat::Debug => format!("{:?}", command.command),
+                        DumpFormat::Asm => command.assembly.clone(),
+                        DumpFormat::Wiki => command.command.to_wiki_string(),
+                        DumpFormat::Stable => unreachable!(),
+                    };
+                    let description = match command.arguments {
+                        Some(arguments) => format!("{description} arguments: {arguments}"),
+                        None => description,
+                    };
+                    let meaning = describe_immediate(&command.command).or_else(|| {
+                        (command.command.command == bluray::Command::Branch(Branch::Resume))
+                            .then(describe_resume)
+                    });
+                    let description = match meaning {
+                        Some(meaning) => format!("{description} ({meaning})"),
+                        None => description,
+                    };
+                    let description = match command.file_offset.filter(|_| self.offsets) {
+                        Some(offset) => format!("at {offset:#x}: {description}"),
+                        None => description,
+                    };
+                    if self.grouped {
+                        writeln!(out, "    navigation command #{j} {description}")?;
+                    } else {
+                        writeln!(
+                            out,
+                            "movie object #{i} navigation command #{j} {description}"
+                        )?;
+                    }
+                }
+            }
+            writeln!(
+                out,
+                "movie object extension data: {:02x?}",
+                file.extension_data
+            )?;
+        }
+        if self.gpr_xref {
+            for (gpr, references) in file.gpr_references() {
+                let references = references
+                    .iter()
+                    .map(|reference| {
+                        let access = match reference.access {
+                            Access::Read => "read",
+                            Access::Write => "write",
+                            Access::ReadWrite => "read/write",
+                        };
+                        format!(
+                            "{},{} ({access})",
+                            reference.movie_object_index, reference.navigation_command_index
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                writeln!(out, "gpr{gpr}: {references}")?;
+            }
+        }
+        if self.fail_on_warning {
+            let warnings = file.warnings();
+            for warning in &warnings {
+                eprintln!("warning: {warning}");
+            }
+            anyhow::ensure!(warnings.is_empty(), "{} warning(s)", warnings.len());
+        }
+        Ok(())
     }
-    println!("movie object extension data: {:02x?}", file.extension_data);
 }
 
-fn test(file: MovieObjectFile) {
-    for (i, object) in (0..).zip(file.movie_objects.movie_objects.iter()) {
-        for (j, command) in (0..).zip(object.navigation_commands.iter()) {
-            match (
-                &command.operand_count,
-                &command.destination,
-                &command.source,
-            ) {
-                (OperandCount::DestinationAndSource, _, &Operand::Psr(source))
-                    if source == 19 || source == 20 =>
-                {
-                    println!("movie object #{i} navigation command #{j} {command:?}");
-                }
+/// Summarizes the file for `dump --info`: its path, its size on disk, its version, and how many
+/// movie objects and navigation commands it has.
+fn summarize(path: &Path, model: &DiscModel) -> std::io::Result<Vec<String>> {
+    Ok(vec![
+        format!("path: {}", path.display()),
+        format!("file size: {}", std::fs::metadata(path)?.len()),
+        format!("version: {}", model.version),
+        format!("movie objects: {}", model.movie_objects.len()),
+        format!(
+            "navigation commands: {}",
+            model
+                .movie_objects
+                .iter()
+                .map(|object| object.navigation_commands.len())
+                .sum::<usize>()
+        ),
+    ])
+}
+
+/// Prints the movie objects in `DumpFormat::Stable`, e.g.
+/// "00000 00002  48400200 80000014 00000002  EQ psr20, 2".
+fn print_stable(
+    out: &mut impl Write,
+    file: &MovieObjectFile,
+    model: &DiscModel,
+    since: u16,
+    until: usize,
+    offsets: bool,
+) -> std::io::Result<()> {
+    let offset = |offset: Option<usize>| match offset.filter(|_| offsets) {
+        Some(offset) => format!("{offset:#010x}  "),
+        None => String::new(),
+    };
+    writeln!(out, "version {}", model.version)?;
+    writeln!(out, "header {}", hex::encode(&file.header))?;
+    for object in &model.movie_objects {
+        let i = object.index;
+        if i < since || usize::from(i) > until {
+            continue;
+        }
+        writeln!(
+            out,
+            "{i:05}        {}flags {:#06x}",
+            offset(object.file_offset),
+            object.flags
+        )?;
+        for command in &object.navigation_commands {
+            let words = command
+                .command
+                .raw_bytes
+                .chunks_exact(4)
+                .map(hex::encode)
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(
+                out,
+                "{i:05} {:05}  {}{words}  {}",
+                command.index,
+                offset(command.file_offset),
+                command.command
+            )?;
+        }
+    }
+    match file.extension_data.as_slice() {
+        [] => writeln!(out, "extension data none"),
+        data => writeln!(out, "extension data {}", hex::encode(data)),
+    }
+}
+
+/// How often `test --watch` checks the file for changes.
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// How many more times `test --watch` reads a changed file that can't be parsed, e.g. because
+/// it's still being written, before waiting for its next change.
+const WATCH_RETRIES: u32 = 3;
+
+/// Polls the file every `interval` until its stamp differs from `stamp`, then parses it and
+/// updates `stamp`. A read that fails is retried, `interval` apart, up to `WATCH_RETRIES` times.
+fn wait_for_change(
+    path: &Path,
+    stamp: &mut (std::time::SystemTime, u64),
+    interval: std::time::Duration,
+) -> MovieObjectFile {
+    loop {
+        std::thread::sleep(interval);
+        // The file may briefly not exist, e.g. while an editor replaces it.
+        if file_stamp(path).is_ok_and(|current| current == *stamp) {
+            continue;
+        }
+        for attempt in 0..=WATCH_RETRIES {
+            if attempt > 0 {
+                std::thread::sleep(interval);
+            }
+            // Take the stamp before reading, so that a write during the read counts as another
+            // change.
+            let Ok(current) = file_stamp(path) else {
+                continue;
+            };
+            *stamp = current;
+            match MovieObjectFile::open(path) {
+                Ok(file) => return file,
+                Err(err) if attempt == WATCH_RETRIES => eprintln!(
+                    "{:#}; waiting for the next change",
+                    anyhow::Error::from(err)
+                ),
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+/// The modification time and size of a file, which change when it's written.
+fn file_stamp(path: &Path) -> std::io::Result<(std::time::SystemTime, u64)> {
+    let metadata = std::fs::metadata(path)?;
+    Ok((metadata.modified()?, metadata.len()))
+}
+
+/// Describes a movie object's flags, e.g. "0x8001: resume intention, reserved bits 0x0001".
+fn describe_flags(object: &MovieObjectModel) -> String {
+    let mut names: Vec<_> = object
+        .flag_names
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    if object.undocumented_flags != 0 {
+        names.push(format!("reserved bits {:#06x}", object.undocumented_flags));
+    }
+    if names.is_empty() {
+        format!("{:#06x}", object.flags)
+    } else {
+        format!("{:#06x}: {}", object.flags, names.join(", "))
+    }
+}
+
+/// Describes the value a disc compares PSR19 or PSR20 against.
+fn describe_expectation(psr: u8, value: u32) -> String {
+    match describe_value(psr, value) {
+        Some(description) => format!("disc expects {description}"),
+        None => format!("compared against {value:#x}"),
+    }
+}
+
+/// Describes a PSR19 or PSR20 value as a country or region(s), e.g. "region A or B".
+fn describe_value(psr: u8, value: u32) -> Option<String> {
+    let value = Operand::Immediate(value);
+    match psr {
+        20 => value
+            .as_region_mask()
+            .filter(|regions| !regions.is_empty())
+            .map(|regions| {
+                format!(
+                    "region {}",
+                    regions
+                        .iter()
+                        .map(|region| format!("{region:?}"))
+                        .collect::<Vec<_>>()
+                        .join(" or ")
+                )
+            }),
+        19 => value
+            .as_ascii_pair()
+            .map(|country| format!("country {country}")),
+        _ => None,
+    }
+}
+
+/// Describes the PSRs that `RESUME` restores from their backups, per the PSR roles, e.g.
+/// "restores psr4 (title) from psr36, ...".
+fn describe_resume() -> String {
+    let restored: Vec<_> = (36..=44)
+        .filter_map(|backup| {
+            let role = Operand::Psr(backup).psr_role()?.strip_prefix("backup ")?;
+            Some(format!("psr{} ({role}) from psr{backup}", backup - 32))
+        })
+        .collect();
+    format!("restores {}", restored.join(", "))
+}
+
+/// Interprets a navigation command's immediate operand in context, e.g. as a region when it's
+/// compared against PSR20, or as a negative number when it's added.
+fn describe_immediate(command: &NavigationCommand) -> Option<String> {
+    if !matches!(command.operand_count, OperandCount::DestinationAndSource) {
+        return None;
+    }
+    match (command.command, command.destination, command.source) {
+        (bluray::Command::Compare(_), Operand::Psr(psr), other)
+        | (bluray::Command::Compare(_), other, Operand::Psr(psr)) => {
+            describe_value(psr, other.as_u32()?)
+        }
+        (bluray::Command::Set(Set::Add | Set::Sub), _, source) => source
+            .as_i32()
+            .filter(|value| value.is_negative())
+            .map(|value| format!("signed {value}")),
+        _ => None,
+    }
+}
+
+fn print_duplicates(
+    out: &mut impl Write,
+    file: &MovieObjectFile,
+    findings: &[RegionFinding],
+) -> std::io::Result<()> {
+    let mut groups: BTreeMap<(String, bool), BTreeSet<u16>> = BTreeMap::new();
+    for finding in findings {
+        let Some(value) = finding.compared else {
+            continue;
+        };
+        let i = finding.movie_object_index;
+        let operator = match file
+            .command(i, finding.navigation_command_index)
+            .unwrap()
+            .command
+        {
+            bluray::Command::Compare(Compare::Bc) => "&",
+            bluray::Command::Compare(Compare::Eq) => "==",
+            bluray::Command::Compare(Compare::Ne) => "!=",
+            bluray::Command::Compare(Compare::Ge) => ">=",
+            bluray::Command::Compare(Compare::Gt) => ">",
+            bluray::Command::Compare(Compare::Le) => "<=",
+            bluray::Command::Compare(Compare::Lt) => "<",
+            _ => continue,
+        };
+        let immediate = Operand::Immediate(value);
+        let (subject, description) = match finding.psr {
+            19 => ("country", immediate.as_ascii_pair()),
+            _ => (
+                "region",
+                immediate
+                    .as_region_mask()
+                    .filter(|regions| !regions.is_empty())
+                    .map(|regions| {
+                        regions
+                            .iter()
+                            .map(|region| format!("{region:?}"))
+                            .collect::<Vec<_>>()
+                            .join("|")
+                    }),
+            ),
+        };
+        let value = description.unwrap_or_else(|| format!("{value:#x}"));
+        let to_error_screen = file
+            .gate(i, finding.navigation_command_index)
+            .is_some_and(|gate| file.gate_fails_to_error_screen(&gate));
+        groups
+            .entry((format!("{subject}{operator}{value}"), to_error_screen))
+            .or_default()
+            .insert(i);
+    }
+    let mut any = false;
+    for ((check, to_error_screen), objects) in groups {
+        if objects.len() < 2 {
+            continue;
+        }
+        any = true;
+        let objects = objects
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        if to_error_screen {
+            writeln!(
+                out,
+                "{check} check branching to a wrong region screen appears in objects {objects}."
+            )?;
+        } else {
+            writeln!(out, "{check} check appears in objects {objects}.")?;
+        }
+    }
+    if !any {
+        writeln!(out, "no duplicate checks")?;
+    }
+    Ok(())
+}
+
+fn print_csv(out: &mut impl Write, findings: &[RegionFinding]) -> std::io::Result<()> {
+    // Quotes a field if needed, per RFC 4180.
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+    writeln!(out, "movie_object,navigation_command,psr,kind,expected")?;
+    for finding in findings {
+        let kind = match finding.kind {
+            RegionFindingKind::DirectRead => "direct-read".to_string(),
+            RegionFindingKind::IndirectViaGpr(gpr) => format!("indirect-via-gpr{gpr}"),
+            RegionFindingKind::WriteAnomaly => "write-anomaly".to_string(),
+            RegionFindingKind::AsciiCountryCompare => "ascii-country-compare".to_string(),
+        };
+        let expected = finding
+            .compared
+            .map(|value| {
+                describe_value(finding.psr, value).unwrap_or_else(|| format!("{value:#x}"))
+            })
+            .unwrap_or_default();
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            finding.movie_object_index,
+            finding.navigation_command_index,
+            finding.psr,
+            field(&kind),
+            field(&expected)
+        )?;
+    }
+    Ok(())
+}
+
+/// Describes region and country checks in plain English, one sentence per gate: a load of the
+/// PSR into a GPR and the compare of that GPR are described together.
+fn explain(file: &MovieObjectFile, findings: &[RegionFinding]) -> Vec<String> {
+    let mut sentences = vec![];
+    let mut gates = HashSet::new();
+    for finding in findings {
+        let (i, j) = (finding.movie_object_index, finding.navigation_command_index);
+        let subject = match finding.psr {
+            19 if finding.kind == RegionFindingKind::AsciiCountryCompare => {
+                "a value that is probably the player's country"
+            }
+            19 => "the player's country (PSR19)",
+            _ => "the player's region (PSR20)",
+        };
+        if finding.kind == RegionFindingKind::WriteAnomaly {
+            sentences.push(format!(
+                "Movie object {i} writes to {subject}, which should be read-only."
+            ));
+            continue;
+        }
+        // The finding is either the compare itself or loads the value that a later compare
+        // checks.
+        let commands = &file.movie_objects.movie_objects[usize::from(i)].navigation_commands;
+        let Some(compare_index) = (j..)
+            .zip(&commands[usize::from(j)..])
+            .find(|(_, command)| matches!(command.command, bluray::Command::Compare(_)))
+            .map(|(index, _)| index)
+        else {
+            sentences.push(format!(
+                "Movie object {i} reads {subject}, but doesn't appear to compare it."
+            ));
+            continue;
+        };
+        if !gates.insert((i, compare_index)) {
+            continue;
+        }
+        let compare = file.command(i, compare_index).unwrap();
+        let expected = finding
+            .compared
+            .or(match (compare.destination, compare.source) {
+                (Operand::Immediate(value), _) | (_, Operand::Immediate(value)) => Some(value),
+                _ => None,
+            });
+        let expected = expected.map(|value| {
+            describe_value(finding.psr, value).unwrap_or_else(|| format!("{value:#x}"))
+        });
+        let condition = match (compare.command, expected) {
+            (bluray::Command::Compare(Compare::Eq), Some(expected)) => {
+                format!("if it is {expected}")
+            }
+            (bluray::Command::Compare(Compare::Ne), Some(expected)) => {
+                format!("if it isn't {expected}")
+            }
+            (bluray::Command::Compare(Compare::Bc), Some(expected)) => {
+                format!("if it passes a bitwise check against {expected}")
+            }
+            (_, Some(expected)) => format!("depending on how it compares to {expected}"),
+            (_, None) => "depending on its value".to_string(),
+        };
+        let action = match file.gate(i, compare_index) {
+            None => {
+                "would run the next navigation command, but the compare is the last one".to_string()
+            }
+            Some(gate) => match gate.target {
+                Some(target) if file.gate_fails_to_error_screen(&gate) => format!(
+                    "jumps to object {target} which plays something and then stops playback, \
+                     probably a wrong region screen"
+                ),
+                Some(target) => format!("jumps to object {target}"),
+                None => format!(
+                    "runs navigation command #{} ({})",
+                    gate.guarded_index,
+                    file.command(i, gate.guarded_index).unwrap()
+                ),
+            },
+        };
+        sentences.push(format!(
+            "Movie object {i} reads {subject} and, {condition}, {action}."
+        ));
+    }
+    sentences
+}
+
+/// The findings that lock a disc to a region or country, which decide the exit code of test:
+/// every read of PSR19 or PSR20, but not writes to them.
+fn locking_checks(findings: &[RegionFinding]) -> impl Iterator<Item = &RegionFinding> {
+    findings
+        .iter()
+        .filter(|finding| finding.kind != RegionFindingKind::WriteAnomaly)
+}
+
+/// The exit code for the checks: 0 if there are none, otherwise `EXIT_REGION_LOCKED` and/or
+/// `EXIT_COUNTRY_LOCKED`.
+fn status(findings: &[RegionFinding]) -> u8 {
+    locking_checks(findings).fold(0, |status, finding| match finding.psr {
+        19 => status | EXIT_COUNTRY_LOCKED,
+        _ => status | EXIT_REGION_LOCKED,
+    })
+}
+
+impl TestArgs {
+    /// Returns the exit code: 0 if no checks were found, otherwise `EXIT_REGION_LOCKED` and/or
+    /// `EXIT_COUNTRY_LOCKED`.
+    fn exec(self, path: &Path, file: MovieObjectFile) -> anyhow::Result<u8> {
+        if self.watch {
+            return self.watch(path, file);
+        }
+        if self.by_title {
+            let index = IndexFile::open(&bluray::index_path(path));
+            return Ok(self.report_by_title(&file, index, &mut std::io::stdout().lock())?);
+        }
+        if !self.check_backup {
+            return Ok(self.report(&file, &mut std::io::stdout().lock())?);
+        }
+        self.report_with_backup(path, &file, &mut std::io::stdout().lock())
+    }
+
+    /// Reports on both the file and its backup, warning first if they differ at all. Returns the
+    /// exit code for the checks found in either.
+    fn report_with_backup(
+        &self,
+        path: &Path,
+        file: &MovieObjectFile,
+        out: &mut impl Write,
+    ) -> anyhow::Result<u8> {
+        let backup_path = bluray::backup_path(path);
+        let backup = MovieObjectFile::open(&backup_path)?;
+        if backup.serialize() != file.serialize() {
+            writeln!(
+                out,
+                "WARNING: {} differs from {}; a disc patched in only one place may still be region locked",
+                backup_path.display(),
+                path.display()
+            )?;
+        }
+        writeln!(out, "{}:", path.display())?;
+        let status = self.report(file, out)?;
+        writeln!(out, "{}:", backup_path.display())?;
+        Ok(status | self.report(&backup, out)?)
+    }
+
+    /// Reports, then waits for the file to change and reports again, until interrupted.
+    fn watch(&self, path: &Path, mut file: MovieObjectFile) -> anyhow::Result<u8> {
+        let mut stamp = file_stamp(path)?;
+        loop {
+            // Clear the screen and move the cursor to the top left.
+            print!("\x1b[2J\x1b[H");
+            println!("{} (watching for changes):", path.display());
+            self.report(&file, &mut std::io::stdout().lock())?;
+            std::io::stdout().flush()?;
+            file = wait_for_change(path, &mut stamp, WATCH_INTERVAL);
+        }
+    }
+
+    /// The checks to report, as selected by the options.
+    fn findings(&self, file: &MovieObjectFile) -> Vec<RegionFinding> {
+        let mut findings = file.region_checks();
+        if self.ascii_country_compare {
+            findings.extend(file.ascii_country_compares());
+            findings.sort_by_key(|finding| {
+                (finding.movie_object_index, finding.navigation_command_index)
+            });
+        }
+        findings
+            .into_iter()
+            .filter(|finding| match finding.psr {
+                19 => !self.region_only,
+                20 => !self.country_only,
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Reports the checks by the titles that reach them, falling back to their movie object.
+    fn report_by_title(
+        &self,
+        file: &MovieObjectFile,
+        index: Result<IndexFile, IndexError>,
+        out: &mut impl Write,
+    ) -> std::io::Result<u8> {
+        let findings = self.findings(file);
+        let index = match index {
+            Ok(index) => Some(index),
+            Err(err) => {
+                writeln!(
+                    out,
+                    "no title mapping ({:#}); reporting movie objects instead",
+                    anyhow::Error::from(err)
+                )?;
+                None
+            }
+        };
+        for finding in locking_checks(&findings) {
+            let i = finding.movie_object_index;
+            let j = finding.navigation_command_index;
+            let subject = match finding.psr {
+                19 => "country",
+                _ => "region",
+            };
+            let command = file.command(i, j).unwrap();
+            let entry_points = index
+                .as_ref()
+                .map(|index| index.entry_points_reaching(file, i))
+                .unwrap_or_default();
+            if entry_points.is_empty() {
+                let unreached = if index.is_some() {
+                    ", not reachable from any title"
+                } else {
+                    ""
+                };
+                writeln!(
+                    out,
+                    "{subject} check in movie object #{i} navigation command #{j}{unreached}: {command}"
+                )?;
+            } else {
+                let entry_points: Vec<_> = entry_points.iter().map(ToString::to_string).collect();
+                writeln!(
+                    out,
+                    "{subject} check reachable from {}: {command}",
+                    entry_points.join(", ")
+                )?;
+            }
+        }
+        Ok(status(&findings))
+    }
+
+    fn report(&self, file: &MovieObjectFile, out: &mut impl Write) -> std::io::Result<u8> {
+        let findings = self.findings(file);
+        let status = status(&findings);
+        if self.trace_detection {
+            for DetectionTrace {
+                movie_object_index: i,
+                navigation_command_index: j,
+                flagged,
+                reason,
+            } in file.trace_detection()
+            {
+                let verdict = if flagged { "flagged" } else { "skipped" };
+                writeln!(
+                    out,
+                    "movie object #{i} navigation command #{j} {verdict}: {reason}"
+                )?;
+            }
+            return Ok(status);
+        }
+        if self.count_only {
+            let count = locking_checks(&findings).count();
+            writeln!(out, "{count}")?;
+            return Ok(status);
+        }
+        if self.report_format == ReportFormat::Csv {
+            print_csv(out, &findings)?;
+            return Ok(status);
+        }
+        if self.explain {
+            for sentence in explain(file, &findings) {
+                writeln!(out, "{sentence}")?;
+            }
+            return Ok(status);
+        }
+        if self.group_duplicates {
+            print_duplicates(out, file, &findings)?;
+            return Ok(status);
+        }
+        if file.movie_objects.movie_objects.is_empty() {
+            writeln!(out, "no movie objects, no region checks")?;
+            return Ok(status);
+        }
+        for RegionFinding {
+            movie_object_index: i,
+            navigation_command_index: j,
+            psr,
+            kind,
+            compared,
+        } in findings
+        {
+            let command = file.command(i, j).unwrap();
+            match kind {
+                RegionFindingKind::DirectRead => writeln!(
+                    out,
+                    "movie object #{i} navigation command #{j} reads PSR{psr}: {command:?}"
+                )?,
+                RegionFindingKind::IndirectViaGpr(gpr) => writeln!(
+                    out,
+                    "movie object #{i} navigation command #{j} compares PSR{psr} via gpr{gpr}: {command:?}"
+                )?,
                 // PSR19 and PSR20 are read-only, so they should only appear as source operands.
                 // Nonetheless, log out any other instance, even if it's unusual.
-                (OperandCount::DestinationAndSource, &Operand::Psr(dest), _)
-                    if dest == 19 || dest == 20 =>
-                {
-                    println!("UNEXPECTED: movie object #{i} navigation command #{j} {command:?}");
-                }
-                (_, &Operand::Psr(dest), _) if dest == 19 || dest == 20 => {
-                    println!("UNEXPECTED: movie object #{i} navigation command #{j} {command:?}");
-                }
-                (_, &Operand::Psr(source), _) if source == 19 || source == 20 => {
-                    println!("UNEXPECTED: movie object #{i} navigation command #{j} {command:?}");
+                RegionFindingKind::WriteAnomaly => writeln!(
+                    out,
+                    "UNEXPECTED: movie object #{i} navigation command #{j} writes PSR{psr}: {command:?}"
+                )?,
+                RegionFindingKind::AsciiCountryCompare => writeln!(
+                    out,
+                    "movie object #{i} navigation command #{j} is likely a country check for {}: {command:?}",
+                    compared
+                        .map(Operand::Immediate)
+                        .and_then(Operand::as_ascii_pair)
+                        .unwrap()
+                )?,
+            }
+            if let Some(value) = compared {
+                writeln!(out, "  {}", describe_expectation(psr, value))?;
+            }
+            if let Some(gate) = file.gate(i, j)
+                && file.gate_fails_to_error_screen(&gate)
+            {
+                writeln!(
+                    out,
+                    "  branches to movie object #{}, probably a wrong region screen",
+                    gate.target.unwrap()
+                )?;
+            }
+            if self.context > 0 {
+                let commands =
+                    &file.movie_objects.movie_objects[usize::from(i)].navigation_commands;
+                let j = usize::from(j);
+                let first = j.saturating_sub(usize::from(self.context));
+                let last = (j + usize::from(self.context)).min(commands.len() - 1);
+                for (k, command) in (first..).zip(&commands[first..=last]) {
+                    let marker = if k == j { '>' } else { ' ' };
+                    writeln!(out, "  {marker} #{k}: {command}")?;
                 }
-                (_, _, _) => continue,
             }
         }
+        let computed_gates = if self.country_only {
+            vec![]
+        } else {
+            file.computed_gates()
+        };
+        for ComputedGate {
+            movie_object_index: i,
+            compare_index: j,
+            gpr,
+            arithmetic_index,
+        } in computed_gates
+        {
+            writeln!(
+                out,
+                "advanced gate: movie object #{i} navigation command #{j} compares the region against gpr{gpr}, computed at runtime by navigation command #{arithmetic_index}; rewriting PSR20 may not neutralize it"
+            )?;
+        }
+        for ProfileCheck {
+            movie_object_index: i,
+            navigation_command_index: j,
+            via_gpr,
+            compared,
+        } in file.profile_checks()
+        {
+            let via = via_gpr.map_or(String::new(), |gpr| format!(" via gpr{gpr}"));
+            writeln!(
+                out,
+                "movie object #{i} navigation command #{j} checks the player profile (PSR31){via}: {:?}",
+                file.command(i, j).unwrap()
+            )?;
+            if let Some(value) = compared {
+                writeln!(
+                    out,
+                    "  compared against {value:#x}: {}",
+                    bluray::describe_profile(value)
+                )?;
+            }
+        }
+        Ok(status)
     }
 }
 
-impl RemoveArgs {
-    fn exec(self, original_path: &Path, mut file: MovieObjectFile) -> anyhow::Result<()> {
-        let nop_patches: HashSet<_> = self.nop_patch.into_iter().collect();
-        // TODO: A better design would avoid re-parsing this from the raw bytes.
-        const NOP_COMMAND_BYTES: [u8; 12] = [0; 12];
-        file.movie_objects.movie_objects = (0..)
-            .zip(file.movie_objects.movie_objects)
-            .map(
-                |(
-                    movie_object_index,
-                    MovieObject {
-                        header,
-                        navigation_commands,
-                    },
-                )| {
-                    let navigation_commands = (0..)
-                        .zip(navigation_commands)
-                        .map(|(navigation_command_index, command)| {
-                            if nop_patches.contains(&NavigationCommandLocator {
-                                movie_object_index,
-                                navigation_command_index,
-                            }) {
-                                return NavigationCommand::from_bytes(&NOP_COMMAND_BYTES).unwrap();
-                            }
-                            // Both PSR19 (country) and PSR20 (region) are read-only, so no need to
-                            // check the destination operand at all.
-                            match (&command.operand_count, &command.source) {
-                                (OperandCount::DestinationAndSource, &Operand::Psr(19)) => {
-                                    let mut raw_bytes = command.raw_bytes;
-                                    // Set the "source is immediate" flag
-                                    raw_bytes[1] |= 1 << 6;
-                                    raw_bytes[10..12].copy_from_slice(self.country.as_bytes());
-                                    NavigationCommand::from_bytes(&raw_bytes).unwrap()
-                                }
-                                (OperandCount::DestinationAndSource, &Operand::Psr(20)) => {
-                                    let mut raw_bytes = command.raw_bytes;
-                                    // Set the "source is immediate" flag
-                                    raw_bytes[1] |= 1 << 6;
-                                    raw_bytes[8..12]
-                                        .copy_from_slice(&(self.region as u32).to_be_bytes());
-                                    NavigationCommand::from_bytes(&raw_bytes).unwrap()
-                                }
-                                _ => command,
-                            }
-                        })
-                        .collect();
-                    MovieObject {
-                        header,
-                        navigation_commands,
-                    }
-                },
+fn dump_command(
+    out: &mut impl Write,
+    file: &MovieObjectFile,
+    locator: NavigationCommandLocator,
+) -> anyhow::Result<()> {
+    let i = locator.movie_object_index;
+    let j = locator.navigation_command_index;
+    let model = file.model();
+    let object = model
+        .movie_objects
+        .get(usize::from(i))
+        .ok_or(EditError::NoSuchMovieObject(i))?;
+    let command = object
+        .navigation_commands
+        .get(usize::from(j))
+        .ok_or(EditError::NoSuchNavigationCommand(i, j))?;
+    writeln!(out, "movie object #{i} navigation command #{j}")?;
+    if let Some(offset) = command.file_offset {
+        writeln!(out, "offset: {offset:#x}")?;
+    }
+    writeln!(
+        out,
+        "raw bytes: {}",
+        hex::encode(&command.command.raw_bytes)
+    )?;
+    writeln!(out, "decoded: {:?}", command.command)?;
+    writeln!(out, "assembly: {}", command.assembly)?;
+    if let Some(arguments) = command.arguments {
+        writeln!(out, "arguments: {arguments}")?;
+    }
+    if let Some(meaning) = describe_immediate(&command.command) {
+        writeln!(out, "meaning: {meaning}")?;
+    }
+    if command.command.command == bluray::Command::Branch(Branch::Resume) {
+        writeln!(out, "meaning: {}", describe_resume())?;
+    }
+    Ok(())
+}
+
+/// Prints a pass/fail line per invariant and returns the exit status: 1 if any invariant is
+/// violated.
+fn validate(file: &MovieObjectFile) -> u8 {
+    let mut status = 0;
+    for check in file.validate() {
+        if check.failures.is_empty() {
+            println!("pass: {}", check.name);
+            continue;
+        }
+        status = EXIT_INVALID;
+        println!("FAIL: {}", check.name);
+        for failure in check.failures {
+            println!("  {failure}");
+        }
+    }
+    status
+}
+
+fn extract_gates(out: &mut impl Write, file: &MovieObjectFile) -> std::io::Result<()> {
+    let mut seen = HashSet::new();
+    for finding in file.region_checks() {
+        if finding.kind == RegionFindingKind::WriteAnomaly {
+            continue;
+        }
+        let Some(gate) = file.gate(finding.movie_object_index, finding.navigation_command_index)
+        else {
+            continue;
+        };
+        if !seen.insert((gate.movie_object_index, gate.compare_index)) {
+            continue;
+        }
+        writeln!(
+            out,
+            "movie object #{} navigation command #{}:",
+            gate.movie_object_index, gate.compare_index
+        )?;
+        for k in file.gate_commands(&gate) {
+            let command = file.command(gate.movie_object_index, k).unwrap();
+            let hex = command
+                .raw_bytes
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(out, "  #{k:<4} {hex}  {command}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Hashes the canonical form of the movie objects, so rips that differ only in bytes players
+/// ignore, e.g. reserved bytes, nop operands, or extension data, get the same fingerprint.
+fn fingerprint(mut file: MovieObjectFile) -> String {
+    file.canonicalize();
+    sha256::hex_digest(&file.serialize_body())
+}
+
+/// Collects the distinct values that region (PSR20) and country (PSR19) checks compare against.
+fn expected_values(file: &MovieObjectFile) -> (BTreeSet<u32>, BTreeSet<u32>) {
+    let mut regions = BTreeSet::new();
+    let mut countries = BTreeSet::new();
+    for finding in file.region_checks() {
+        match (finding.psr, finding.compared) {
+            (20, Some(value)) => {
+                regions.insert(value);
+            }
+            (19, Some(value)) => {
+                countries.insert(value);
+            }
+            _ => {}
+        }
+    }
+    (regions, countries)
+}
+
+/// Reads the region, and the country if there's a single one, that the reference disc at `path`
+/// expects, e.g. an original disc from the region to patch another disc for.
+fn reference_values(path: &Path) -> anyhow::Result<(Region, Option<String>)> {
+    let file = MovieObjectFile::open(&bluray::resolve_path(path)?)?;
+    let (regions, countries) = expected_values(&file);
+    let list = |values: &BTreeSet<u32>| {
+        values
+            .iter()
+            .map(|value| format!("{value:#x}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let region = match Vec::from_iter(&regions)[..] {
+        [] => anyhow::bail!(
+            "reference disc {} has no region check to take the region from",
+            path.display()
+        ),
+        [&value] => Region::value_variants()
+            .iter()
+            .copied()
+            .find(|region| region.to_psr_value() == value)
+            .with_context(|| {
+                format!(
+                    "reference disc {} compares the region against {value:#x}, which isn't a single region",
+                    path.display()
+                )
+            })?,
+        _ => anyhow::bail!(
+            "reference disc {} compares the region against several values: {}",
+            path.display(),
+            list(&regions)
+        ),
+    };
+    let country = match Vec::from_iter(&countries)[..] {
+        [] => None,
+        [&value] => Some(Operand::Immediate(value).as_ascii_pair().with_context(|| {
+            format!(
+                "reference disc {} compares the country against {value:#x}, which isn't a country code",
+                path.display()
             )
+        })?),
+        _ => anyhow::bail!(
+            "reference disc {} compares the country against several values: {}",
+            path.display(),
+            list(&countries)
+        ),
+    };
+    Ok((region, country))
+}
+
+fn expectations(file: MovieObjectFile) {
+    let (regions, countries) = expected_values(&file);
+    if regions.is_empty() && countries.is_empty() {
+        println!("no region or country comparisons found");
+    }
+    for value in regions {
+        println!("region {value:#x}: {}", describe_expectation(20, value));
+    }
+    for value in countries {
+        println!("country {value:#x}: {}", describe_expectation(19, value));
+    }
+}
+
+impl TemplateArgs {
+    fn exec(self, path: &Path) -> anyhow::Result<()> {
+        let bytes = MovieObjectFile::blank(self.objects, self.commands_per_object)
+            .context("too many movie objects or navigation commands")?
+            .serialize();
+        MovieObjectFile::from_bytes(&bytes).context("generated file does not parse")?;
+        std::fs::OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("failed to create {}", path.display()))?
+            .write_all(&bytes)?;
+        println!("wrote {} bytes to {}", bytes.len(), path.display());
+        Ok(())
+    }
+}
+
+impl RemoveArgs {
+    /// Patches each disc in --from-list under `root`, then summarizes the failures.
+    fn exec_batch(mut self, root: &Path, strict: bool) -> anyhow::Result<u8> {
+        let list_path = self.from_list.take().unwrap();
+        let output_root = self.output.output_path.take().unwrap();
+        let list = std::fs::read_to_string(&list_path)
+            .with_context(|| format!("failed to read {}", list_path.display()))?;
+        let entries: Vec<_> = list
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
             .collect();
+        let mut failures = vec![];
+        for entry in &entries {
+            println!("{entry}:");
+            let result = (|| {
+                let path = bluray::resolve_path(&root.join(entry))?;
+                let relative = path.strip_prefix(root).with_context(|| {
+                    format!("{} is not under {}", path.display(), root.display())
+                })?;
+                let output_path = output_root.join(relative);
+                std::fs::create_dir_all(output_path.parent().unwrap())?;
+                let file = MovieObjectFile::open(&path)?;
+                let file = if strict { file.strict()? } else { file };
+                let mut args = self.clone();
+                args.output.output_path = Some(output_path);
+                args.exec(&path, file, &mut std::io::stdout().lock())
+            })();
+            if let Err(err) = result {
+                println!("failed: {err:#}");
+                failures.push(entry);
+            }
+        }
+        println!(
+            "patched {} of {} discs",
+            entries.len() - failures.len(),
+            entries.len()
+        );
+        for entry in &failures {
+            println!("failed: {entry}");
+        }
+        anyhow::ensure!(
+            failures.is_empty(),
+            "{} of {} discs failed",
+            failures.len(),
+            entries.len()
+        );
+        Ok(0)
+    }
+
+    fn exec(
+        mut self,
+        original_path: &Path,
+        mut file: MovieObjectFile,
+        out: &mut impl Write,
+    ) -> anyhow::Result<()> {
+        // Force-pass leaves the region and country reads alone, so there's nothing to rewrite
+        // them to.
+        anyhow::ensure!(
+            self.mode == Mode::Rewrite
+                || (self.region.is_none()
+                    && self.country.is_none()
+                    && self.preset.is_none()
+                    && self.reference.is_none()),
+            "--region, --country, --preset, and --reference can't be used with --mode=force-pass"
+        );
+        if let Some(Preset { country, region }) = self.preset.take() {
+            self.country.get_or_insert(country);
+            self.region.get_or_insert(region);
+        }
+        if let Some(reference) = &self.reference {
+            let (region, country) = reference_values(reference)?;
+            writeln!(
+                out,
+                "reference disc {} expects region {region:?}{}",
+                reference.display(),
+                country
+                    .as_ref()
+                    .map(|country| format!(" and country {country}"))
+                    .unwrap_or_default()
+            )?;
+            self.region.get_or_insert(region);
+            if let Some(country) = country {
+                self.country.get_or_insert(country);
+            }
+        }
+        if self.validate_values
+            && let Some(country) = &self.country
+            && !bluray::is_country_code(country)
+        {
+            let message = format!("{country} is not an assigned ISO 3166-1 alpha-2 country code");
+            anyhow::ensure!(self.force, "{message}; use --force to patch anyway");
+            eprintln!("warning: {message}");
+        }
+        anyhow::ensure!(
+            self.mode != Mode::Rewrite || (self.region.is_some() && self.country.is_some()),
+            "--region and --country (or --preset or --reference) are required unless --mode=force-pass"
+        );
+        anyhow::ensure!(
+            !self.minimal_diff || self.mode == Mode::Rewrite,
+            "--minimal-diff requires --mode=rewrite"
+        );
+        // Force-pass leaves the region and country reads in place, so the check would always
+        // find them.
+        anyhow::ensure!(
+            !self.verify_roundtrip || self.mode == Mode::Rewrite,
+            "--verify-roundtrip requires --mode=rewrite"
+        );
+        anyhow::ensure!(
+            !self.two_pass || self.mode == Mode::ForcePass,
+            "--two-pass requires --mode=force-pass"
+        );
+        anyhow::ensure!(
+            !self.minimal_diff || self.reserved_policy == ReservedPolicy::Preserve,
+            "--minimal-diff requires --reserved-policy=preserve"
+        );
+        let input = file.serialize();
+        writeln!(out, "input SHA-256: {}", sha256::hex_digest(&input))?;
+        let mut patches = HashMap::new();
+        for nop_patch in self.nop_patch {
+            match nop_patch {
+                NopPatch::NavigationCommand(locator) => {
+                    patches.insert(locator, PatchAction::Nop);
+                }
+                NopPatch::MovieObject(movie_object_index) => {
+                    let object = file
+                        .movie_objects
+                        .movie_objects
+                        .get(usize::from(movie_object_index))
+                        .ok_or(EditError::NoSuchMovieObject(movie_object_index))?;
+                    patches.extend((0..object.navigation_commands.len()).map(|j| {
+                        let locator = NavigationCommandLocator {
+                            movie_object_index,
+                            navigation_command_index: u16::try_from(j).unwrap(),
+                        };
+                        (locator, PatchAction::Nop)
+                    }));
+                }
+            }
+        }
+        // With --two-pass, detection runs again on the patched output. Force-pass leaves the
+        // region and country reads in place, so a finding only counts while the gate it leads
+        // to is unpatched. Once a gate's compare is patched, the same finding can lead to the
+        // next compare instead, e.g. one that the first gate hid.
+        let max_passes = if self.two_pass { 2 } else { 1 };
+        let mut gates = HashSet::new();
+        let mut passes = 0;
+        let mut patched = 0;
+        let remaining = loop {
+            let findings: Vec<_> = MovieObjectFile::from_bytes(&file.serialize())?
+                .region_checks()
+                .into_iter()
+                .filter(|finding| finding.kind != RegionFindingKind::WriteAnomaly)
+                .collect();
+            let pending = match self.mode {
+                Mode::Rewrite => findings.len(),
+                Mode::ForcePass => findings
+                    .iter()
+                    .filter_map(|finding| {
+                        file.gate(finding.movie_object_index, finding.navigation_command_index)
+                    })
+                    .filter(|gate| {
+                        [gate.compare_index, gate.guarded_index].into_iter().all(
+                            |navigation_command_index| {
+                                !patches.contains_key(&NavigationCommandLocator {
+                                    movie_object_index: gate.movie_object_index,
+                                    navigation_command_index,
+                                })
+                            },
+                        )
+                    })
+                    .map(|gate| (gate.movie_object_index, gate.compare_index))
+                    .collect::<HashSet<_>>()
+                    .len(),
+            };
+            if passes == max_passes || (passes > 0 && pending == 0) {
+                break pending;
+            }
+            passes += 1;
+            if self.mode == Mode::ForcePass {
+                for finding in &findings {
+                    let (i, j) = (finding.movie_object_index, finding.navigation_command_index);
+                    let Some(gate) = file.gate(i, j) else {
+                        if passes == 1 {
+                            writeln!(
+                                out,
+                                "not forcing check at movie object #{i} navigation command #{j} to pass: no compare and guarded command follow it"
+                            )?;
+                        }
+                        continue;
+                    };
+                    // Several findings, e.g. a load and its compare, can belong to the same gate,
+                    // and a gate found in an earlier pass is already handled.
+                    if !gates.insert((gate.movie_object_index, gate.compare_index)) {
+                        continue;
+                    }
+                    let Some((navigation_command_index, action)) = file.force_pass_patch(&gate)
+                    else {
+                        writeln!(
+                            out,
+                            "not forcing gate at movie object #{i} navigation command #{} to pass: can't tell which of its branches passes",
+                            gate.compare_index
+                        )?;
+                        continue;
+                    };
+                    let locator = NavigationCommandLocator {
+                        movie_object_index: i,
+                        navigation_command_index,
+                    };
+                    if let Entry::Vacant(entry) = patches.entry(locator) {
+                        entry.insert(action);
+                        let replacement = match action {
+                            PatchAction::GoTo(target) => format!("GOTO {target}"),
+                            _ => "a nop".to_string(),
+                        };
+                        writeln!(
+                            out,
+                            "forcing check to pass: replacing movie object #{i} navigation command #{navigation_command_index} with {replacement}"
+                        )?;
+                    }
+                }
+            }
+            patched += patch_commands(&mut file, &patches, self.country.as_deref(), self.region)?;
+        };
+        if self.two_pass {
+            writeln!(
+                out,
+                "passes: {passes}, remaining region or country gates: {remaining}"
+            )?;
+        }
+        anyhow::ensure!(
+            patched > 0 || !self.require_changes,
+            "no region, country, or nop patch changed anything; nothing was written"
+        );
+
+        if self.canonicalize_nops {
+            let mut count = 0;
+            for command in file
+                .movie_objects
+                .movie_objects
+                .iter_mut()
+                .flat_map(|object| object.navigation_commands.iter_mut())
+            {
+                if command.command == bluray::Command::Branch(Branch::Nop)
+                    && command.raw_bytes != NavigationCommand::nop().raw_bytes
+                {
+                    *command = NavigationCommand::nop();
+                    count += 1;
+                }
+            }
+            writeln!(out, "canonicalized {count} nops")?;
+        }
+
+        // Delete from the back so earlier deletions don't shift the locations of later ones.
+        let mut deletions = self.delete;
+        deletions.sort_by_key(|locator| {
+            std::cmp::Reverse((locator.movie_object_index, locator.navigation_command_index))
+        });
+        deletions.dedup();
+        for locator in deletions {
+            file.apply_patch(
+                locator.movie_object_index,
+                locator.navigation_command_index,
+                PatchAction::Delete,
+            )?;
+        }
+
+        // Insert from the back so earlier insertions don't shift the locations of later ones.
+        let mut insertions = self.insert;
+        insertions.sort_by_key(|insertion| {
+            std::cmp::Reverse((
+                insertion.locator.movie_object_index,
+                insertion.locator.navigation_command_index,
+            ))
+        });
+        for Insertion { locator, command } in insertions {
+            file.insert_command(
+                locator.movie_object_index,
+                locator.navigation_command_index,
+                command,
+            )?;
+        }
+
+        if self.collapse_nops {
+            writeln!(out, "collapsed {} nops", file.collapse_nops())?;
+        }
+
+        if let Some(version) = self.assume_version {
+            file.set_version(version);
+        }
+
+        if self.reserved_policy == ReservedPolicy::Zero {
+            writeln!(out, "zeroed {} reserved bytes", file.zero_reserved())?;
+        }
+        if self.normalize_immediate_flags {
+            writeln!(
+                out,
+                "normalized immediate flags of {} navigation commands",
+                file.normalize_immediate_flags()
+            )?;
+        }
+
+        if self.verify_roundtrip {
+            let reparsed = MovieObjectFile::from_bytes(&file.serialize())
+                .context("round-trip self-check failed: patched file does not parse")?;
+            let remaining = reparsed
+                .region_checks()
+                .iter()
+                .filter(|finding| finding.kind != RegionFindingKind::WriteAnomaly)
+                .count();
+            anyhow::ensure!(
+                remaining == 0,
+                "round-trip self-check failed: {remaining} region or country checks remain"
+            );
+            writeln!(out, "round-trip self-check passed")?;
+        }
+
+        let output = file.serialize();
+        // Bytes that differ, plus any bytes added or removed at the end.
+        let changed = input
+            .iter()
+            .zip(&output)
+            .filter(|(before, after)| before != after)
+            .count()
+            + input.len().abs_diff(output.len());
+        if self.minimal_diff {
+            anyhow::ensure!(
+                output.len() == input.len(),
+                "--minimal-diff would change the file length"
+            );
+            writeln!(out, "bytes changed: {changed}")?;
+        }
+        if let Some(max_changes) = self.max_changes {
+            anyhow::ensure!(
+                changed <= max_changes,
+                "patch would change {changed} bytes, more than --max-changes {max_changes}; nothing was written"
+            );
+        }
+
+        let bytes = if self.body_only {
+            file.serialize_body()
+        } else {
+            file.serialize()
+        };
+        let bytes = if self.base64 {
+            base64::encode(&bytes).into_bytes()
+        } else {
+            bytes
+        };
+        // Read before writing, since --in-place replaces the input.
+        let input_mtime = if self.preserve_mtime {
+            Some(std::fs::metadata(original_path)?.modified()?)
+        } else {
+            None
+        };
+        let mut written = self.output.commit(original_path, &bytes, !self.no_fsync)?;
+        if let Some(mtime) = input_mtime {
+            // Any backup is a link to or copy of the input, so setting its modification time
+            // again is harmless.
+            for path in &written {
+                std::fs::File::options()
+                    .write(true)
+                    .open(path)?
+                    .set_modified(mtime)
+                    .with_context(|| {
+                        format!("failed to set modification time of {}", path.display())
+                    })?;
+            }
+        }
+        if let Some(bdmv_dir) = self.bdmv_dir {
+            written.extend(write_bdmv_dir(original_path, &bdmv_dir, &bytes)?);
+        }
+        if let Some(manifest) = self.manifest {
+            let mut contents = String::new();
+            for path in written {
+                let bytes = std::fs::read(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                contents += &format!(
+                    "{}  {}  {}\n",
+                    sha256::hex_digest(&bytes),
+                    bytes.len(),
+                    path.display()
+                );
+            }
+            std::fs::write(&manifest, contents)
+                .with_context(|| format!("failed to write {}", manifest.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Applies `patches`, e.g. nops, to their navigation commands, and rewrites reads of PSR19 and
+/// PSR20 to the given country and region. Returns the number of commands that changed.
+fn patch_commands(
+    file: &mut MovieObjectFile,
+    patches: &HashMap<NavigationCommandLocator, PatchAction>,
+    country: Option<&str>,
+    region: Option<Region>,
+) -> Result<usize, PatchError> {
+    let command_bytes = |file: &MovieObjectFile| -> Vec<[u8; 12]> {
+        file.movie_objects
+            .movie_objects
+            .iter()
+            .flat_map(|object| &object.navigation_commands)
+            .map(|command| command.raw_bytes)
+            .collect()
+    };
+    let before = command_bytes(file);
+
+    let mut patches: Vec<_> = patches.iter().collect();
+    patches
+        .sort_by_key(|(locator, _)| (locator.movie_object_index, locator.navigation_command_index));
+    for (locator, &action) in patches {
+        file.apply_patch(
+            locator.movie_object_index,
+            locator.navigation_command_index,
+            action,
+        )?;
+    }
+
+    let actions: Vec<_> = country
+        .map(|country| PatchAction::SetCountry(country.as_bytes().try_into().unwrap()))
+        .into_iter()
+        .chain(region.map(|region| PatchAction::SetRegion(region.to_psr_value())))
+        .collect();
+    let lengths: Vec<_> = file
+        .movie_objects
+        .movie_objects
+        .iter()
+        .map(|object| u16::try_from(object.navigation_commands.len()).unwrap())
+        .collect();
+    for (i, len) in (0..).zip(lengths) {
+        for j in 0..len {
+            for &action in &actions {
+                match file.apply_patch(i, j, action) {
+                    Ok(()) | Err(PatchError::NoPsrRead(..)) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+    }
+
+    let after = command_bytes(file);
+    Ok(before
+        .iter()
+        .zip(&after)
+        .filter(|(before, after)| before != after)
+        .count())
+}
+
+/// Writes the patched MovieObject.bdmv and copies of the index.bdmv files next to the original
+/// into a new BDMV directory. Returns the paths of all files written.
+fn write_bdmv_dir(
+    original_path: &Path,
+    bdmv_dir: &Path,
+    bytes: &[u8],
+) -> anyhow::Result<Vec<PathBuf>> {
+    let movie_object_path = bdmv_dir.join("MovieObject.bdmv");
+    let backup_path = bluray::backup_path(&movie_object_path);
+    std::fs::create_dir_all(backup_path.parent().unwrap())
+        .with_context(|| format!("failed to create {}", bdmv_dir.display()))?;
+    let mut written = vec![];
+    for path in [movie_object_path, backup_path] {
+        std::fs::OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("failed to create {}", path.display()))?
+            .write_all(bytes)?;
+        written.push(path);
+    }
+    for (source, destination) in [
+        (
+            original_path.with_file_name("index.bdmv"),
+            bdmv_dir.join("index.bdmv"),
+        ),
+        (
+            original_path.with_file_name("BACKUP").join("index.bdmv"),
+            bdmv_dir.join("BACKUP").join("index.bdmv"),
+        ),
+    ] {
+        if !source.exists() {
+            eprintln!("warning: {} not found, not copying it", source.display());
+            continue;
+        }
+        anyhow::ensure!(
+            !destination.exists(),
+            "{} already exists",
+            destination.display()
+        );
+        std::fs::copy(&source, &destination)
+            .with_context(|| format!("failed to copy {}", source.display()))?;
+        written.push(destination);
+    }
+    println!("wrote BDMV directory {}", bdmv_dir.display());
+    Ok(written)
+}
+
+/// Writes `bytes` to a temporary file next to `path`, so it can be renamed over `path` once it's
+/// complete. The data is flushed to disk first unless `fsync` is false. Returns the temporary
+/// file's path.
+fn write_temporary(path: &Path, bytes: &[u8], fsync: bool) -> anyhow::Result<PathBuf> {
+    let mut name = path
+        .file_name()
+        .context("output path has no file name")?
+        .to_owned();
+    name.push(".tmp");
+    let temporary_path = path.with_file_name(name);
+    let result = std::fs::File::create(&temporary_path).and_then(|mut file| {
+        file.write_all(bytes)?;
+        if fsync { file.sync_all() } else { Ok(()) }
+    });
+    if let Err(error) = result {
+        let _ = std::fs::remove_file(&temporary_path);
+        return Err(error).with_context(|| format!("failed to write {}", temporary_path.display()));
+    }
+    Ok(temporary_path)
+}
 
-        self.output.commit(original_path, file)
+/// Flushes the directory entry changes in the directory containing `path`, e.g. a rename, to
+/// disk. Only Unix can open a directory to sync it; elsewhere, this does nothing.
+fn sync_parent_directory(path: &Path) -> std::io::Result<()> {
+    if cfg!(unix) {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        std::fs::File::open(parent)?.sync_all()?;
     }
+    Ok(())
 }
 
 impl Output {
-    fn commit(self, original_path: &Path, file: MovieObjectFile) -> anyhow::Result<()> {
+    /// Returns the paths of all files written, including any backup. The output is written to a
+    /// temporary file and then renamed over the destination in a single step, so a crash leaves
+    /// either the original or the complete output. For --in-place, the backup is a hard link to
+    /// the original, or a copy where links aren't supported, so the original path always exists.
+    fn commit(
+        self,
+        original_path: &Path,
+        bytes: &[u8],
+        fsync: bool,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let mut written = vec![];
+        let output_path = if self.in_place {
+            original_path.to_owned()
+        } else {
+            self.output_path.unwrap()
+        };
+        // This is racy but good enough.
+        if !self.in_place && std::fs::exists(&output_path)? {
+            anyhow::bail!("{} already exists", output_path.display());
+        }
+        let temporary_path = write_temporary(&output_path, bytes, fsync)?;
         if self.in_place {
             let backup_path = original_path.with_extension("bdmv.orig");
             // This is racy but good enough.
-            if !std::fs::exists(&backup_path)?
+            let backup_exists = std::fs::exists(&backup_path)?;
+            if !backup_exists
                 || Confirm::new()
                     .with_prompt(format!(
                         "{} already exists; overwrite backup?",
@@ -224,7 +2218,13 @@ impl Output {
                     ))
                     .interact()?
             {
-                std::fs::rename(original_path, &backup_path)?;
+                if backup_exists {
+                    std::fs::remove_file(&backup_path)?;
+                }
+                std::fs::hard_link(original_path, &backup_path)
+                    .or_else(|_| std::fs::copy(original_path, &backup_path).map(|_| ()))
+                    .with_context(|| format!("failed to back up to {}", backup_path.display()))?;
+                written.push(backup_path);
             } else if !Confirm::new()
                 .with_prompt(format!(
                     "Continue without backing up {}?",
@@ -232,21 +2232,1362 @@ impl Output {
                 ))
                 .interact()?
             {
+                std::fs::remove_file(&temporary_path)?;
                 println!("Cancelled by user, exiting!");
-                return Ok(());
+                return Ok(written);
             }
-            std::fs::OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(original_path)?
-        } else {
-            std::fs::OpenOptions::new()
-                .create_new(true)
-                .write(true)
-                .open(self.output_path.unwrap())?
         }
-        .write_all(&file.serialize())?;
-        Ok(())
+        std::fs::rename(&temporary_path, &output_path)
+            .with_context(|| format!("failed to write {}", output_path.display()))?;
+        if fsync {
+            sync_parent_directory(&output_path).with_context(|| {
+                format!("failed to sync the directory of {}", output_path.display())
+            })?;
+        }
+        written.push(output_path);
+        println!("output SHA-256: {}", sha256::hex_digest(bytes));
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Cli, Command, EXIT_COUNTRY_LOCKED, EXIT_ERROR, EXIT_REGION_LOCKED, IndexFile, Output,
+        bluray, describe_immediate, dump_command, exit_status, expected_values, explain,
+        extract_gates, file_stamp, fingerprint, first_difference, locking_checks, parse_preset,
+        patch_commands, reencode, run, sha256, summarize, wait_for_change, write_temporary,
+    };
+    use crate::bluray::{MovieObjectFile, Region, testing};
+    use clap::Parser;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::time::Duration;
+
+    /// Runs dump with the given options and returns what it prints, even if it fails.
+    fn try_dump(file: MovieObjectFile, options: &[&str]) -> (String, anyhow::Result<()>) {
+        let cli = Cli::try_parse_from(
+            ["bd-region", "MovieObject.bdmv", "dump"]
+                .iter()
+                .chain(options),
+        )
+        .unwrap();
+        let Command::Dump(args) = cli.command else {
+            unreachable!()
+        };
+        let mut out = vec![];
+        let result = args.exec(Path::new("MovieObject.bdmv"), file, &mut out);
+        (String::from_utf8(out).unwrap(), result)
+    }
+
+    /// Runs dump with the given options and returns what it prints.
+    fn dump(file: MovieObjectFile, options: &[&str]) -> String {
+        let (output, result) = try_dump(file, options);
+        result.unwrap();
+        output
+    }
+
+    /// Writes `input` to MovieObject.bdmv in `dir`, then runs remove on it with the given options,
+    /// writing to patched.bdmv in `dir`.
+    fn remove(dir: &Path, input: &[u8], options: &[&str]) -> anyhow::Result<u8> {
+        let path = dir.join("MovieObject.bdmv");
+        std::fs::write(&path, input).unwrap();
+        let output_path = dir.join("patched.bdmv");
+        let path = path.to_str().unwrap();
+        let output_path = output_path.to_str().unwrap();
+        run(Cli::try_parse_from(
+            ["bd-region", path, "remove"]
+                .iter()
+                .chain(options)
+                .chain([&output_path]),
+        )?)
+    }
+
+    /// Like [`remove`], but returns what was printed along with the result.
+    fn remove_printing(dir: &Path, input: &[u8], options: &[&str]) -> (String, anyhow::Result<()>) {
+        let path = dir.join("MovieObject.bdmv");
+        std::fs::write(&path, input).unwrap();
+        let output_path = dir.join("patched.bdmv");
+        let cli = Cli::try_parse_from(
+            ["bd-region", path.to_str().unwrap(), "remove"]
+                .iter()
+                .chain(options)
+                .chain([&output_path.to_str().unwrap()]),
+        )
+        .unwrap();
+        let Command::Remove(args) = cli.command else {
+            unreachable!()
+        };
+        let mut out = vec![];
+        let result = args.exec(&path, MovieObjectFile::from_bytes(input).unwrap(), &mut out);
+        (String::from_utf8(out).unwrap(), result)
+    }
+
+    #[test]
+    fn two_pass_forces_a_gate_that_the_first_one_hid() {
+        let dir = testing::scratch_directory("two-pass");
+        // The second gate compares a copy of the region, which detection doesn't follow, so it's
+        // only found from the load once the first gate's compare is patched.
+        let input = testing::bytes(&[
+            &[
+                "MOV gpr1, psr20",
+                "NE gpr1, 2",
+                "JUMP_OBJECT 1",
+                "MOV gpr2, gpr1",
+                "NE gpr2, 2",
+                "JUMP_OBJECT 1",
+                "PLAY_PL 0",
+            ],
+            &["PLAY_PL 1", "TERMINATE"],
+        ]);
+        let patched = || {
+            let output = std::fs::read(dir.join("patched.bdmv")).unwrap();
+            testing::assembly(&MovieObjectFile::from_bytes(&output).unwrap(), 0)
+        };
+
+        let (output, result) = remove_printing(&dir, &input, &["--mode", "force-pass"]);
+        result.unwrap();
+        assert!(!output.contains("passes:"));
+        assert_eq!(
+            patched()[1..5],
+            ["GOTO 3", "JUMP_OBJECT 1", "MOV gpr2, gpr1", "NE gpr2, 2"]
+        );
+        std::fs::remove_file(dir.join("patched.bdmv")).unwrap();
+
+        let (output, result) =
+            remove_printing(&dir, &input, &["--mode", "force-pass", "--two-pass"]);
+        result.unwrap();
+        let lines: Vec<_> = output.lines().skip(1).collect();
+        assert_eq!(
+            lines,
+            [
+                "forcing check to pass: replacing movie object #0 navigation command #1 with GOTO 3",
+                "forcing check to pass: replacing movie object #0 navigation command #4 with GOTO 6",
+                "passes: 2, remaining region or country gates: 0",
+            ]
+        );
+        assert_eq!(
+            patched(),
+            [
+                "MOV gpr1, psr20",
+                "GOTO 3",
+                "JUMP_OBJECT 1",
+                "MOV gpr2, gpr1",
+                "GOTO 6",
+                "JUMP_OBJECT 1",
+                "PLAY_PL 0",
+            ]
+        );
+
+        let options = ["--region", "B", "--country", "US", "--two-pass"];
+        let (_, result) = remove_printing(&dir, &input, &options);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "--two-pass requires --mode=force-pass"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn force_pass_rejects_region_and_country_values() {
+        let dir = testing::scratch_directory("force-pass-values");
+        let input = testing::bytes(&[&["MOV gpr1, psr20", "EQ gpr1, 2", "GOTO 3", "PLAY_PL 0"]]);
+        for options in [
+            &["--region", "B"][..],
+            &["--country", "US"],
+            &["--preset", "US-A"],
+        ] {
+            let options = [&["--mode", "force-pass"], options].concat();
+            let (_, result) = remove_printing(&dir, &input, &options);
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "--region, --country, --preset, and --reference can't be used with --mode=force-pass"
+            );
+            assert!(!dir.join("patched.bdmv").exists());
+        }
+        // Without them, only the gate's compare is patched, and the load of PSR20 is left alone.
+        let (_, result) = remove_printing(&dir, &input, &["--mode", "force-pass"]);
+        result.unwrap();
+        let output = std::fs::read(dir.join("patched.bdmv")).unwrap();
+        assert_eq!(
+            testing::assembly(&MovieObjectFile::from_bytes(&output).unwrap(), 0),
+            ["MOV gpr1, psr20", "NOP", "GOTO 3", "PLAY_PL 0"]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn template_reopens_with_the_requested_shape() {
+        let dir = testing::scratch_directory("template");
+        let path = dir.join("MovieObject.bdmv");
+        let cli = Cli::try_parse_from([
+            "bd-region",
+            path.to_str().unwrap(),
+            "template",
+            "--objects",
+            "2",
+            "--commands-per-object",
+            "3",
+        ]);
+        assert_eq!(run(cli.unwrap()).unwrap(), 0);
+        let file = MovieObjectFile::open(&path).unwrap();
+        assert_eq!(file.movie_objects.movie_objects.len(), 2);
+        for i in 0..2 {
+            assert_eq!(testing::assembly(&file, i), ["NOP"; 3]);
+        }
+        assert!(file.warnings().is_empty());
+        // The template never overwrites an existing file.
+        let cli = Cli::try_parse_from(["bd-region", path.to_str().unwrap(), "template"]);
+        assert!(run(cli.unwrap()).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn interrupted_write_leaves_original_intact() {
+        let dir = testing::scratch_directory("interrupted");
+        let path = dir.join("MovieObject.bdmv");
+        std::fs::write(&path, b"original").unwrap();
+        // Stopping after the temporary file is written, before the rename, is the same as being
+        // killed mid-write as far as the original is concerned.
+        let temporary_path = write_temporary(&path, b"patched", false).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"original");
+        assert_eq!(std::fs::read(&temporary_path).unwrap(), b"patched");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn in_place_write_keeps_a_backup() {
+        let dir = testing::scratch_directory("in-place");
+        let path = dir.join("MovieObject.bdmv");
+        std::fs::write(&path, b"original").unwrap();
+        let output = Output {
+            output_path: None,
+            in_place: true,
+        };
+        let written = output.commit(&path, b"patched", true).unwrap();
+        let backup_path = dir.join("MovieObject.bdmv.orig");
+        assert_eq!(written, [backup_path.clone(), path.clone()]);
+        assert_eq!(std::fs::read(&path).unwrap(), b"patched");
+        assert_eq!(std::fs::read(&backup_path).unwrap(), b"original");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Runs test with the given options and returns what it prints and its exit code.
+    fn test(file: &MovieObjectFile, options: &[&str]) -> (String, u8) {
+        let cli = Cli::try_parse_from(
+            ["bd-region", "MovieObject.bdmv", "test"]
+                .iter()
+                .chain(options),
+        )
+        .unwrap();
+        let Command::Test(args) = cli.command else {
+            unreachable!()
+        };
+        let mut out = vec![];
+        let status = args.report(file, &mut out).unwrap();
+        (String::from_utf8(out).unwrap(), status)
+    }
+
+    #[test]
+    fn exit_status_distinguishes_errors_from_locks() {
+        let dir = testing::scratch_directory("exit-status");
+        let path = dir.join("MovieObject.bdmv");
+        let test = |input: &[u8]| {
+            std::fs::write(&path, input).unwrap();
+            exit_status(run(Cli::try_parse_from([
+                "bd-region",
+                path.to_str().unwrap(),
+                "test",
+            ])
+            .unwrap()))
+        };
+        assert_eq!(test(&testing::bytes(&[&["NOP"]])), 0);
+        assert_eq!(test(&testing::bytes(&[&["EQ psr20, 2"]])), 1);
+        assert_eq!(test(&testing::bytes(&[&["EQ psr19, 0x6a70"]])), 2);
+        assert_eq!(
+            test(&testing::bytes(&[&["EQ psr20, 2", "EQ psr19, 0x6a70"]])),
+            3
+        );
+        assert_eq!(test(b"MOBJ0200 but truncated"), EXIT_ERROR);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn context_is_clamped_to_the_object() {
+        let file = testing::file(&[
+            &["PLAY_PL 0"],
+            &[
+                "NOP",
+                "PLAY_PL 1",
+                "EQ psr20, 2",
+                "JUMP_OBJECT 0",
+                "TERMINATE",
+            ],
+        ]);
+        let context = |options| {
+            let (output, _) = test(&file, options);
+            output
+                .lines()
+                .filter(|line| line.starts_with("  ") && line.contains(": "))
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            context(&["--context", "1"]),
+            [
+                "    #1: PLAY_PL 1",
+                "  > #2: EQ psr20, 2",
+                "    #3: JUMP_OBJECT 0"
+            ]
+        );
+        // The window stops at the ends of the movie object rather than spilling into the others.
+        assert_eq!(
+            context(&["--context", "5"]),
+            [
+                "    #0: NOP",
+                "    #1: PLAY_PL 1",
+                "  > #2: EQ psr20, 2",
+                "    #3: JUMP_OBJECT 0",
+                "    #4: TERMINATE"
+            ]
+        );
+        assert!(context(&[]).is_empty());
+    }
+
+    #[test]
+    fn extracted_gate_has_load_compare_and_branch() {
+        let file = testing::file(&[&[
+            "MOV gpr1, psr20",
+            "PLAY_PL 1",
+            "NE gpr1, 2",
+            "JUMP_OBJECT 1",
+            "TERMINATE",
+        ]]);
+        let mut out = vec![];
+        extract_gates(&mut out, &file).unwrap();
+        // The unrelated PLAY_PL between the load and the compare is left out.
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "movie object #0 navigation command #2:\n\
+             \x20 #0    50 00 00 01 00 00 00 01 80 00 00 14  MOV gpr1, psr20\n\
+             \x20 #2    48 40 03 00 00 00 00 01 00 00 00 02  NE gpr1, 2\n\
+             \x20 #3    21 80 00 00 00 00 00 01 00 00 00 00  JUMP_OBJECT 1\n"
+        );
+    }
+
+    #[test]
+    fn zero_movie_objects_round_trip() {
+        let bytes = testing::bytes(&[]);
+        assert_eq!(&bytes[40..], [0, 0, 0, 6, 0, 0, 0, 0, 0, 0]);
+        let file = MovieObjectFile::from_bytes(&bytes).unwrap();
+        assert!(file.movie_objects.movie_objects.is_empty());
+        assert_eq!(file.serialize(), bytes);
+        let (output, status) = test(&file, &[]);
+        assert_eq!(output, "no movie objects, no region checks\n");
+        assert_eq!(status, 0);
+    }
+
+    #[test]
+    fn equivalent_rips_have_the_same_fingerprint() {
+        let objects: &[&[&str]] = &[&["MOV gpr1, psr20", "NE gpr1, 2", "NOP", "JUMP_OBJECT 1"]];
+        let clean = testing::bytes(objects);
+        let mut dirty = clean.clone();
+        // A reserved header byte, an undocumented movie object flag, the operands of the nop at
+        // byte 78, and extension data.
+        dirty[20] = 0xff;
+        dirty[51] = 0x01;
+        dirty[85] = 0x07;
+        dirty.extend_from_slice(b"extension data");
+        let fingerprint_of =
+            |bytes: &[u8]| fingerprint(MovieObjectFile::from_bytes(bytes).unwrap());
+        assert_eq!(fingerprint_of(&clean), fingerprint_of(&dirty));
+        // A change that players act on changes the fingerprint.
+        let different =
+            testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 4", "NOP", "JUMP_OBJECT 1"]]);
+        assert_ne!(fingerprint_of(&clean), fingerprint_of(&different));
+    }
+
+    #[test]
+    fn trace_explains_each_command() {
+        let file = testing::file(&[&["MOV gpr1, psr20", "NE gpr1, 2", "PLAY_PL 1", "NOP"]]);
+        let (output, status) = test(&file, &["--trace-detection"]);
+        assert_eq!(
+            output,
+            "movie object #0 navigation command #0 flagged: source is PSR20; gpr1 now holds PSR20\n\
+             movie object #0 navigation command #1 flagged: compares gpr1, which holds PSR20, as the destination\n\
+             movie object #0 navigation command #2 skipped: single operand, which isn't PSR19 or PSR20\n\
+             movie object #0 navigation command #3 skipped: no operands\n"
+        );
+        // Tracing doesn't change the exit status.
+        assert_eq!(status, EXIT_REGION_LOCKED);
+    }
+
+    #[test]
+    fn gates_to_error_screens_are_labeled() {
+        let file = testing::file(&[
+            &[
+                "MOV gpr1, psr20",
+                "NE gpr1, 2",
+                "JUMP_OBJECT 1",
+                "EQ psr20, 2",
+                "JUMP_OBJECT 2",
+            ],
+            // Plays a "wrong region" clip, then stops.
+            &["NOP", "PLAY_PL 99", "TERMINATE"],
+            // The main menu.
+            &["MOV gpr2, 1", "PLAY_PL 1", "JUMP_OBJECT 3", "JUMP_OBJECT 4"],
+        ]);
+        let (output, _) = test(&file, &[]);
+        let labels: Vec<_> = output
+            .lines()
+            .filter(|line| line.contains("wrong region screen"))
+            .collect();
+        // Both the load and the compare of the first gate are labeled; the second gate leads to
+        // the menu.
+        assert_eq!(
+            labels,
+            ["  branches to movie object #1, probably a wrong region screen"; 2]
+        );
+    }
+
+    #[test]
+    fn check_backup_reports_a_backup_left_locked() {
+        let dir = testing::scratch_directory("check-backup");
+        std::fs::create_dir_all(dir.join("BACKUP")).unwrap();
+        let path = dir.join("MovieObject.bdmv");
+        let patched = testing::bytes(&[&["MOV gpr1, 2", "NE gpr1, 2", "JUMP_OBJECT 1"]]);
+        let locked = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "JUMP_OBJECT 1"]]);
+        std::fs::write(&path, &patched).unwrap();
+        std::fs::write(dir.join("BACKUP").join("MovieObject.bdmv"), &locked).unwrap();
+        let cli = Cli::try_parse_from(["bd-region", ".", "test", "--check-backup"]).unwrap();
+        let Command::Test(args) = cli.command else {
+            unreachable!()
+        };
+        let file = MovieObjectFile::open(&path).unwrap();
+        let mut out = vec![];
+        let status = args.report_with_backup(&path, &file, &mut out).unwrap();
+        assert_eq!(status, EXIT_REGION_LOCKED);
+        let output = String::from_utf8(out).unwrap();
+        let backup_path = dir.join("BACKUP").join("MovieObject.bdmv");
+        assert!(output.starts_with(&format!(
+            "WARNING: {} differs from {}",
+            backup_path.display(),
+            path.display()
+        )));
+        // Only the backup's checks are reported, under its own heading.
+        let (primary, backup) = output
+            .split_once(&format!("{}:\n", backup_path.display()))
+            .unwrap();
+        assert!(!primary.contains("PSR20"));
+        assert!(backup.contains("reads PSR20"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn duplicate_checks_are_grouped() {
+        let gate: &[&str] = &["MOV gpr1, psr20", "NE gpr1, 2", "JUMP_OBJECT 4"];
+        let file = testing::file(&[
+            gate,
+            &["EQ psr19, 0x4a50", "PLAY_PL 1"],
+            gate,
+            &["EQ psr19, 0x4a50", "PLAY_PL 2"],
+            &["PLAY_PL 99", "TERMINATE"],
+            gate,
+            // Only once.
+            &["EQ psr20, 1", "PLAY_PL 3"],
+        ]);
+        let (output, _) = test(&file, &["--group-duplicates"]);
+        assert_eq!(
+            output,
+            "country==JP check appears in objects 1, 3.\n\
+             region!=B check branching to a wrong region screen appears in objects 0, 2, 5.\n"
+        );
+    }
+
+    #[test]
+    fn by_title_names_the_titles_reaching_each_check() {
+        let dir = testing::scratch_directory("by-title");
+        let bdmv = dir.join("BDMV");
+        std::fs::create_dir(&bdmv).unwrap();
+        // Title 1 starts at movie object 0, which calls the region gate in movie object 1. The
+        // country check in movie object 3 isn't reachable from any title.
+        let file = testing::file(&[
+            &["CALL_OBJECT 1", "PLAY_PL 0"],
+            &["EQ psr20, 2", "JUMP_OBJECT 2"],
+            &["PLAY_PL 1", "TERMINATE"],
+            &["NE psr19, 0x5553", "JUMP_OBJECT 2"],
+        ]);
+        let by_title = |options: &[&str]| {
+            let cli = Cli::try_parse_from(
+                ["bd-region", "MovieObject.bdmv", "test", "--by-title"]
+                    .iter()
+                    .chain(options),
+            )
+            .unwrap();
+            let Command::Test(args) = cli.command else {
+                unreachable!()
+            };
+            let index = IndexFile::open(&bluray::index_path(&bdmv.join("MovieObject.bdmv")));
+            let mut out = vec![];
+            let status = args.report_by_title(&file, index, &mut out).unwrap();
+            (String::from_utf8(out).unwrap(), status)
+        };
+
+        std::fs::write(bdmv.join("index.bdmv"), testing::index_bytes(2, 2, &[0])).unwrap();
+        assert_eq!(
+            by_title(&[]),
+            (
+                "region check reachable from Title 1: EQ psr20, 2\n\
+                 country check in movie object #3 navigation command #0, not reachable from any title: NE psr19, 21843\n"
+                    .to_string(),
+                EXIT_REGION_LOCKED | EXIT_COUNTRY_LOCKED
+            )
+        );
+
+        std::fs::remove_file(bdmv.join("index.bdmv")).unwrap();
+        let (output, _) = by_title(&["--region-only"]);
+        let (note, output) = output.split_once('\n').unwrap();
+        assert!(note.starts_with(&format!(
+            "no title mapping (IO error for {}: ",
+            bdmv.join("index.bdmv").display()
+        )));
+        assert!(note.ends_with("); reporting movie objects instead"));
+        assert_eq!(
+            output,
+            "region check in movie object #1 navigation command #0: EQ psr20, 2\n"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn watch_stamp_changes_when_the_file_is_rewritten() {
+        let dir = testing::scratch_directory("watch-stamp");
+        let path = dir.join("MovieObject.bdmv");
+        std::fs::write(&path, testing::bytes(&[&["EQ psr20, 2"]])).unwrap();
+        let stamp = file_stamp(&path).unwrap();
+        assert_eq!(file_stamp(&path).unwrap(), stamp);
+        // A different size changes the stamp even if the modification time doesn't.
+        std::fs::write(&path, testing::bytes(&[&["NOP", "NOP"]])).unwrap();
+        assert_ne!(file_stamp(&path).unwrap(), stamp);
+        std::fs::remove_file(&path).unwrap();
+        assert!(file_stamp(&path).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn watch_reports_again_after_the_file_is_rewritten() {
+        let dir = testing::scratch_directory("watch");
+        let path = dir.join("MovieObject.bdmv");
+        std::fs::write(&path, testing::bytes(&[&["EQ psr20, 2"]])).unwrap();
+        let mut stamp = file_stamp(&path).unwrap();
+        let (first, _) = test(&MovieObjectFile::open(&path).unwrap(), &[]);
+
+        std::fs::write(&path, testing::bytes(&[&["NOP", "EQ psr19, 0x5553"]])).unwrap();
+        let file = wait_for_change(&path, &mut stamp, Duration::from_millis(1));
+        assert_eq!(stamp, file_stamp(&path).unwrap());
+        let (second, _) = test(&file, &[]);
+        assert_ne!(second, first);
+        assert!(second.starts_with("movie object #0 navigation command #1 reads PSR19"));
+
+        // A half-written file is read again until it parses.
+        std::fs::write(&path, b"MOBJ0200").unwrap();
+        let writer = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(300));
+                std::fs::write(&path, testing::bytes(&[&["NOP"]])).unwrap();
+            })
+        };
+        let file = wait_for_change(&path, &mut stamp, Duration::from_millis(200));
+        writer.join().unwrap();
+        assert_eq!(testing::assembly(&file, 0), ["NOP"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_reports_expected_values() {
+        let file = testing::file(&[&[
+            "EQ psr20, 2",
+            "NOP",
+            "MOV gpr1, psr19",
+            "NE gpr1, 0x4a50",
+            "TERMINATE",
+        ]]);
+        let (output, status) = test(&file, &[]);
+        let expectations: Vec<_> = output
+            .lines()
+            .filter(|line| line.starts_with("  "))
+            .collect();
+        assert_eq!(
+            expectations,
+            ["  disc expects region B", "  disc expects country JP"]
+        );
+        assert_eq!(status, EXIT_REGION_LOCKED | EXIT_COUNTRY_LOCKED);
+    }
+
+    #[test]
+    fn expectations_list_each_value_once() {
+        let file = testing::file(&[
+            &["EQ psr20, 1", "NOP", "MOV gpr1, psr20", "BC gpr1, 4", "NOP"],
+            &["EQ psr20, 1", "NOP", "EQ psr19, 0x4a50", "NOP"],
+            // Not a compare against an immediate.
+            &["EQ psr20, gpr3", "NOP"],
+        ]);
+        let (regions, countries) = expected_values(&file);
+        assert_eq!(Vec::from_iter(regions), [0x1, 0x4]);
+        assert_eq!(Vec::from_iter(countries), [0x4a50]);
+    }
+
+    #[test]
+    fn test_filters_by_kind_of_check() {
+        let file = testing::file(&[&["EQ psr20, 2", "NOP", "EQ psr19, 0x4a50", "NOP"]]);
+        let checks = |output: String| -> Vec<String> {
+            output
+                .lines()
+                .filter(|line| line.starts_with("movie object"))
+                .map(|line| line.split(':').next().unwrap().to_string())
+                .collect()
+        };
+        let (output, status) = test(&file, &[]);
+        assert_eq!(
+            checks(output),
+            [
+                "movie object #0 navigation command #0 reads PSR20",
+                "movie object #0 navigation command #2 reads PSR19",
+            ]
+        );
+        assert_eq!(status, EXIT_REGION_LOCKED | EXIT_COUNTRY_LOCKED);
+        let (output, status) = test(&file, &["--region-only"]);
+        assert_eq!(
+            checks(output),
+            ["movie object #0 navigation command #0 reads PSR20"]
+        );
+        assert_eq!(status, EXIT_REGION_LOCKED);
+        let (output, status) = test(&file, &["--country-only"]);
+        assert_eq!(
+            checks(output),
+            ["movie object #0 navigation command #2 reads PSR19"]
+        );
+        assert_eq!(status, EXIT_COUNTRY_LOCKED);
+        assert!(
+            Cli::try_parse_from(["bd-region", ".", "test", "--region-only", "--country-only"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn csv_report_has_header_and_row_per_finding() {
+        let file = testing::file(&[&["MOV gpr1, psr20", "NE gpr1, 5", "JUMP_OBJECT 1"]]);
+        let (output, _) = test(&file, &["--report-format", "csv"]);
+        assert_eq!(
+            output,
+            "movie_object,navigation_command,psr,kind,expected\n\
+             0,0,20,direct-read,\n\
+             0,1,20,indirect-via-gpr1,region A or C\n"
+        );
+    }
+
+    #[test]
+    fn immediates_are_described_in_context() {
+        let describe = |assembly: &str| describe_immediate(&assembly.parse().unwrap());
+        assert_eq!(describe("EQ psr20, 2").as_deref(), Some("region B"));
+        assert_eq!(describe("NE 0x4a50, psr19").as_deref(), Some("country JP"));
+        assert_eq!(
+            describe("ADD gpr1, 0xfffffffe").as_deref(),
+            Some("signed -2")
+        );
+        assert_eq!(describe("ADD gpr1, 2"), None);
+        assert_eq!(describe("EQ gpr1, 2"), None);
+    }
+
+    #[test]
+    fn summary_counts_objects_and_commands() {
+        let dir = testing::scratch_directory("summary");
+        let path = dir.join("MovieObject.bdmv");
+        // Extension data isn't part of the model, but still counts towards the file size.
+        let mut bytes = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "NOP"], &["NOP"]]);
+        bytes.extend_from_slice(&[0; 6]);
+        std::fs::write(&path, &bytes).unwrap();
+        let model = MovieObjectFile::open(&path).unwrap().model();
+        assert_eq!(
+            summarize(&path, &model).unwrap(),
+            [
+                format!("path: {}", path.display()),
+                "file size: 112".to_string(),
+                "version: 0200".to_string(),
+                "movie objects: 2".to_string(),
+                "navigation commands: 4".to_string(),
+            ]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn gpr_xref_lists_each_reference() {
+        let file = testing::file(&[&["MOV gpr1, psr20", "NE gpr1, 2"], &["ADD gpr3, gpr1"]]);
+        let output = dump(file, &["--gpr-xref"]);
+        let xref: Vec<_> = output
+            .lines()
+            .filter(|line| line.starts_with("gpr"))
+            .collect();
+        assert_eq!(
+            xref,
+            [
+                "gpr1: 0,0 (write), 0,1 (read), 1,0 (read)",
+                "gpr3: 1,0 (read/write)"
+            ]
+        );
+    }
+
+    #[test]
+    fn stable_dump_is_deterministic() {
+        let objects: &[&[&str]] = &[&["MOV gpr1, psr20", "NE gpr1, 2"], &[]];
+        let stable = dump(testing::file(objects), &["--format", "stable"]);
+        assert_eq!(
+            stable,
+            dump(testing::file(objects), &["--format", "stable"])
+        );
+        assert_eq!(
+            stable,
+            "version 0200\n\
+             header 4d4f424a303230300000000000000000000000000000000000000000000000000000000000000000\n\
+             00000        flags 0x0000\n\
+             00000 00000  50000001 00000001 80000014  MOV gpr1, psr20\n\
+             00000 00001  48400300 00000001 00000002  NE gpr1, 2\n\
+             00001        flags 0x0000\n\
+             extension data none\n"
+        );
+        // Offsets are only there on request.
+        let with_offsets = dump(testing::file(objects), &["--format", "stable", "--offsets"]);
+        assert!(with_offsets.contains("00000 00001  0x00000042  48400300"));
+    }
+
+    #[test]
+    fn resume_is_annotated_with_restored_psrs() {
+        let output = dump(testing::file(&[&["RESUME", "TERMINATE"]]), &[]);
+        let annotated: Vec<_> = output
+            .lines()
+            .filter_map(|line| line.split_once(" (restores "))
+            .map(|(_, restored)| restored)
+            .collect();
+        // PSR41 is reserved, so nothing is restored to PSR9.
+        assert_eq!(
+            annotated,
+            [
+                "psr4 (title) from psr36, psr5 (chapter) from psr37, psr6 (play list) from psr38, \
+                 psr7 (play item) from psr39, psr8 (presentation time) from psr40, \
+                 psr10 (selected button) from psr42, psr11 (menu page) from psr43, \
+                 psr12 (TextST user style) from psr44)"
+            ]
+        );
+    }
+
+    #[test]
+    fn dump_one_prints_a_single_command_in_full() {
+        let file = testing::file(&[&["PLAY_PL 1"], &["MOV gpr1, psr20", "EQ gpr1, 2"]]);
+        let dump_one = |locator: &str| {
+            let mut out = vec![];
+            dump_command(&mut out, &file, locator.parse().unwrap())
+                .map(|()| String::from_utf8(out).unwrap())
+        };
+        assert_eq!(
+            dump_one("1,0").unwrap(),
+            "movie object #1 navigation command #0\n\
+             offset: 0x46\n\
+             raw bytes: 500000010000000180000014\n\
+             decoded: NavigationCommand { command: Set(Move), operand_count: DestinationAndSource, \
+             destination: Gpr(1), source: Psr(20) } with raw bytes: \
+             [50, 00, 00, 01, 00, 00, 00, 01, 80, 00, 00, 14]\n\
+             assembly: MOV gpr1, psr20 (region)\n"
+        );
+        assert_eq!(
+            dump_one("1,2").unwrap_err().to_string(),
+            "movie object #1 has no navigation command #2"
+        );
+        assert_eq!(
+            dump_one("2,0").unwrap_err().to_string(),
+            "no movie object #2"
+        );
+    }
+
+    #[test]
+    fn empty_movie_objects_are_shown() {
+        let objects: &[&[&str]] = &[&["PLAY_PL 1"], &[], &["TERMINATE"]];
+        let bytes = testing::bytes(objects);
+        assert_eq!(
+            MovieObjectFile::from_bytes(&bytes).unwrap().serialize(),
+            bytes
+        );
+        let output = dump(testing::file(objects), &["--format", "asm"]);
+        assert_eq!(
+            output
+                .lines()
+                .filter(|line| line.starts_with("movie object #"))
+                .collect::<Vec<_>>(),
+            [
+                "movie object #0 navigation command #0 PLAY_PL 1",
+                "movie object #1: (no commands)",
+                "movie object #2 navigation command #0 TERMINATE"
+            ]
+        );
+        let output = dump(testing::file(objects), &["--grouped", "--format", "asm"]);
+        assert!(output.contains("movie object #1 (flags 0x0000):\n    (no commands)\n"));
+    }
+
+    #[test]
+    fn grouped_dump_has_a_header_per_object() {
+        let file = testing::file(&[&["MOV gpr1, psr20", "NE gpr1, 2"], &[]]);
+        let output = dump(file, &["--grouped", "--format", "asm"]);
+        let body: Vec<_> = output
+            .lines()
+            .filter(|line| !line.starts_with("movie object header") && !line.contains("byte size"))
+            .collect();
+        assert_eq!(
+            body,
+            [
+                "movie object #0 (flags 0x0000):",
+                "    navigation command #0 MOV gpr1, psr20 (region)",
+                "    navigation command #1 NE gpr1, 2",
+                "movie object #1 (flags 0x0000):",
+                "    (no commands)",
+                "movie object extension data: []",
+            ]
+        );
+    }
+
+    #[test]
+    fn dump_is_limited_to_object_window() {
+        let file = testing::file(&[&["NOP"], &["PLAY_PL 1"], &["PLAY_PL 2"], &["PLAY_PL 3"]]);
+        let output = dump(
+            file,
+            &[
+                "--since-object",
+                "1",
+                "--until-object",
+                "2",
+                "--format",
+                "asm",
+            ],
+        );
+        let commands: Vec<_> = output
+            .lines()
+            .filter(|line| line.contains("navigation command"))
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                "movie object #1 navigation command #0 PLAY_PL 1",
+                "movie object #2 navigation command #0 PLAY_PL 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn raw_header_shows_reserved_bytes() {
+        let mut bytes = testing::bytes(&[&["NOP"]]);
+        bytes[12] = 0xab;
+        bytes[39] = 0xcd;
+        bytes[44..48].copy_from_slice(&[1, 2, 3, 4]);
+        // Reserved flag bits of movie object #0.
+        bytes[50..52].copy_from_slice(&[0x80, 0x01]);
+        let file = MovieObjectFile::from_bytes(&bytes).unwrap();
+        let output = dump(file, &["--raw-header"]);
+        let lines: Vec<_> = output
+            .lines()
+            .filter(|line| line.contains("reserved") || line.contains("flags"))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "header reserved bytes: [ab, 00, 00, 00, 00, 00, 00, 00, 00, 00, 00, 00, 00, 00, \
+                 00, 00, 00, 00, 00, 00, 00, 00, 00, 00, 00, 00, 00, cd]",
+                "movie objects reserved bytes: [01, 02, 03, 04]",
+                "movie object #0 flags: [80, 01]",
+            ]
+        );
+    }
+
+    #[test]
+    fn fail_on_warning_still_dumps() {
+        let warning = || testing::file(&[&["MOV psr20, 2"]]);
+        let (output, result) = try_dump(warning(), &["--fail-on-warning", "--format", "asm"]);
+        assert!(output.contains("movie object #0 navigation command #0 MOV psr20 (region), 2"));
+        assert_eq!(result.unwrap_err().to_string(), "1 warning(s)");
+        let (_, result) = try_dump(warning(), &[]);
+        assert!(result.is_ok());
+        let (_, result) = try_dump(
+            testing::file(&[&["MOV gpr1, psr20"]]),
+            &["--fail-on-warning"],
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn recovered_file_differs_from_original() {
+        let mut bytes = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "NOP"]]);
+        assert_eq!(
+            first_difference(
+                &bytes,
+                &MovieObjectFile::from_bytes(&bytes).unwrap().serialize()
+            ),
+            None
+        );
+        bytes.truncate(bytes.len() - 5);
+        let recovered = MovieObjectFile::from_bytes_recovering(&bytes).unwrap();
+        // The declared length in the movie objects header is the first thing fixed up.
+        assert_eq!(
+            first_difference(&bytes, &recovered.serialize()),
+            Some(40 + 3)
+        );
+    }
+
+    #[test]
+    fn reencode_reports_the_first_tampered_offset() {
+        let dir = testing::scratch_directory("reencode");
+        let path = dir.join("MovieObject.bdmv");
+        let bytes = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "NOP"]]);
+        std::fs::write(&path, &bytes).unwrap();
+        let mut file = MovieObjectFile::from_bytes(&bytes).unwrap();
+        let mut out = vec![];
+        reencode(&mut out, &path, &file).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{}: re-encoded 90 bytes identically\n", path.display())
+        );
+
+        // Flip a bit in the second reserved byte after the movie objects length, at 45.
+        file.movie_objects.reserved[1] ^= 0x01;
+        let mut out = vec![];
+        let err = reencode(&mut out, &path, &file).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{}: re-encoding differs from the original at offset 0x2d",
+                path.display()
+            )
+        );
+        assert!(out.is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn count_includes_indirect_checks() {
+        let file = testing::file(&[&[
+            "MOV gpr1, psr20",
+            "NE gpr1, 2",
+            "NOP",
+            "EQ psr19, 0x4a50",
+            "NOP",
+        ]]);
+        assert_eq!(locking_checks(&file.region_checks()).count(), 3);
+        assert_eq!(
+            test(&file, &["--count-only"]),
+            ("3\n".to_string(), EXIT_REGION_LOCKED | EXIT_COUNTRY_LOCKED)
+        );
+    }
+
+    #[test]
+    fn roundtrip_self_check_catches_broken_patch() {
+        let dir = testing::scratch_directory("self-check");
+        let input = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "JUMP_OBJECT 1"]]);
+        let options = ["--region", "B", "--country", "US", "--verify-roundtrip"];
+        remove(&dir, &input, &options).unwrap();
+        std::fs::remove_file(dir.join("patched.bdmv")).unwrap();
+        // Inserted commands aren't patched, so this one reads the region again.
+        let options = [&options[..], &["--insert", "0,0=MOV gpr2, psr20"]].concat();
+        let err = remove(&dir, &input, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "round-trip self-check failed: 1 region or country checks remain"
+        );
+        assert!(!dir.join("patched.bdmv").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn compares_with_the_psr_as_destination_are_rewritten() {
+        let dir = testing::scratch_directory("psr-destination");
+        let input = testing::bytes(&[&["EQ psr20, 2", "NE psr19, 0x5553", "JUMP_OBJECT 1"]]);
+        let options = ["--region", "B", "--country", "US", "--verify-roundtrip"];
+        remove(&dir, &input, &options).unwrap();
+        let output = std::fs::read(dir.join("patched.bdmv")).unwrap();
+        let output = MovieObjectFile::from_bytes(&output).unwrap();
+        // 21843 is "US".
+        assert_eq!(
+            testing::assembly(&output, 0),
+            ["EQ 2, 2", "NE 21843, 21843", "JUMP_OBJECT 1"]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn canonicalize_nops_zeroes_unused_nop_bytes() {
+        let dir = testing::scratch_directory("canonicalize-nops");
+        let mut input = testing::bytes(&[&["NOP", "NOP", "JUMP_OBJECT 0"]]);
+        // Leftover operands in the first nop.
+        input[58..66].copy_from_slice(&[0, 0, 0, 7, 0, 0, 0, 9]);
+        let options = ["--region", "B", "--country", "US", "--canonicalize-nops"];
+        remove(&dir, &input, &options).unwrap();
+        let output = std::fs::read(dir.join("patched.bdmv")).unwrap();
+        assert_eq!(output[..58], input[..58]);
+        assert_eq!(output[58..66], [0; 8]);
+        assert_eq!(output[66..], input[66..]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn minimal_diff_changes_only_psr_operands_and_flags() {
+        let dir = testing::scratch_directory("minimal-diff");
+        let input = testing::bytes(&[&["NOP", "MOV gpr1, psr20", "EQ psr19, 0x4a50"]]);
+        let options = ["--region", "B", "--country", "US", "--minimal-diff"];
+        remove(&dir, &input, &options).unwrap();
+        let output = std::fs::read(dir.join("patched.bdmv")).unwrap();
+        assert_eq!(output.len(), input.len());
+        let changed: Vec<_> = (0..input.len())
+            .filter(|&i| input[i] != output[i])
+            .collect();
+        // The commands start at 66 and 78: the immediate flag of each is in its second byte,
+        // the source operand of the MOV at 74..78, and the left operand of the EQ at 82..86.
+        assert_eq!(changed, [67, 74, 77, 79, 82, 84, 85]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn manifest_lists_written_files() {
+        let dir = testing::scratch_directory("manifest");
+        let input = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "JUMP_OBJECT 1"]]);
+        let manifest_path = dir.join("manifest.txt");
+        let options = [
+            "--region",
+            "B",
+            "--country",
+            "US",
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+        ];
+        remove(&dir, &input, &options).unwrap();
+        let output_path = dir.join("patched.bdmv");
+        let output = std::fs::read(&output_path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&manifest_path).unwrap(),
+            format!(
+                "{}  {}  {}\n",
+                sha256::hex_digest(&output),
+                output.len(),
+                output_path.display()
+            )
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bdmv_dir_copies_only_existing_files() {
+        let dir = testing::scratch_directory("bdmv-dir");
+        std::fs::write(dir.join("index.bdmv"), b"index").unwrap();
+        let input = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "JUMP_OBJECT 1"]]);
+        let bdmv_dir = dir.join("out").join("BDMV");
+        let options = [
+            "--region",
+            "B",
+            "--country",
+            "US",
+            "--bdmv-dir",
+            bdmv_dir.to_str().unwrap(),
+        ];
+        remove(&dir, &input, &options).unwrap();
+        let patched = std::fs::read(dir.join("patched.bdmv")).unwrap();
+        assert_eq!(
+            std::fs::read(bdmv_dir.join("MovieObject.bdmv")).unwrap(),
+            patched
+        );
+        let backup = bdmv_dir.join("BACKUP");
+        assert_eq!(
+            std::fs::read(backup.join("MovieObject.bdmv")).unwrap(),
+            patched
+        );
+        assert_eq!(
+            std::fs::read(bdmv_dir.join("index.bdmv")).unwrap(),
+            b"index"
+        );
+        // There's no BACKUP/index.bdmv on the disc to copy.
+        assert_eq!(std::fs::read_dir(&bdmv_dir).unwrap().count(), 3);
+        assert_eq!(std::fs::read_dir(&backup).unwrap().count(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn preset_expands_to_region_and_country() {
+        let preset = parse_preset("UK-B").unwrap();
+        assert!(matches!(preset.region, Region::B));
+        assert_eq!(preset.country, "GB");
+        assert!(parse_preset("UKB").is_err());
+        let dir = testing::scratch_directory("preset");
+        let input = testing::bytes(&[&["MOV gpr1, psr20", "MOV gpr2, psr19"]]);
+        remove(&dir, &input, &["--preset", "UK-B"]).unwrap();
+        let output = MovieObjectFile::open(&dir.join("patched.bdmv")).unwrap();
+        // 18242 is 0x4742, "GB".
+        assert_eq!(
+            testing::assembly(&output, 0),
+            ["MOV gpr1, 2", "MOV gpr2, 18242"]
+        );
+        // Either part can be overridden.
+        std::fs::remove_file(dir.join("patched.bdmv")).unwrap();
+        remove(&dir, &input, &["--preset", "UK-B", "--region", "A"]).unwrap();
+        let output = MovieObjectFile::open(&dir.join("patched.bdmv")).unwrap();
+        assert_eq!(
+            testing::assembly(&output, 0),
+            ["MOV gpr1, 1", "MOV gpr2, 18242"]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn max_changes_stops_an_over_broad_patch() {
+        let dir = testing::scratch_directory("max-changes");
+        let input = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "JUMP_OBJECT 1"]]);
+        let options = ["--region", "B", "--country", "US", "--max-changes", "8"];
+        remove(&dir, &input, &options).unwrap();
+        std::fs::remove_file(dir.join("patched.bdmv")).unwrap();
+        let options = [&options[..], &["--nop-patch", "0,*"]].concat();
+        let err = remove(&dir, &input, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "patch would change 13 bytes, more than --max-changes 8; nothing was written"
+        );
+        assert!(!dir.join("patched.bdmv").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn validate_values_rejects_unassigned_countries() {
+        let dir = testing::scratch_directory("validate-values");
+        let input = testing::bytes(&[&["MOV gpr1, psr19", "NE gpr1, 0x4a50", "JUMP_OBJECT 1"]]);
+        let options = ["--region", "A", "--country", "JP", "--validate-values"];
+        remove(&dir, &input, &options).unwrap();
+        std::fs::remove_file(dir.join("patched.bdmv")).unwrap();
+        // "XX" is well-formed, but not assigned to a country.
+        let options = ["--region", "A", "--country", "XX", "--validate-values"];
+        let err = remove(&dir, &input, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "XX is not an assigned ISO 3166-1 alpha-2 country code; use --force to patch anyway"
+        );
+        assert!(!dir.join("patched.bdmv").exists());
+        remove(&dir, &input, &[&options[..], &["--force"]].concat()).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn require_changes_fails_on_a_clean_disc() {
+        let dir = testing::scratch_directory("require-changes");
+        let clean = testing::bytes(&[&["PLAY_PL 1", "TERMINATE"]]);
+        let options = ["--region", "B", "--country", "US", "--require-changes"];
+        let err = remove(&dir, &clean, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no region, country, or nop patch changed anything; nothing was written"
+        );
+        assert!(!dir.join("patched.bdmv").exists());
+        // Without the flag, the unchanged file is still written.
+        remove(&dir, &clean, &options[..4]).unwrap();
+        assert_eq!(std::fs::read(dir.join("patched.bdmv")).unwrap(), clean);
+        std::fs::remove_file(dir.join("patched.bdmv")).unwrap();
+        let locked = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "JUMP_OBJECT 1"]]);
+        remove(&dir, &locked, &options).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reserved_policy_preserves_or_zeroes_reserved_bytes() {
+        let dir = testing::scratch_directory("reserved-policy");
+        let mut input = testing::bytes(&[&["PLAY_PL 1", "MOV gpr1, psr20", "NE gpr1, 2"]]);
+        // A reserved header byte, a reserved byte before the movie objects count, and the
+        // reserved bits of byte 2 of the PLAY_PL.
+        input[20] = 0xaa;
+        input[45] = 0x01;
+        input[56] |= 0x10;
+        let reserved = |bytes: &[u8]| [bytes[20], bytes[45], bytes[56]];
+        let options = ["--region", "B", "--country", "US"];
+        remove(&dir, &input, &options).unwrap();
+        let output = std::fs::read(dir.join("patched.bdmv")).unwrap();
+        assert_eq!(reserved(&output), [0xaa, 0x01, 0x10]);
+        std::fs::remove_file(dir.join("patched.bdmv")).unwrap();
+        let options = [&options[..], &["--reserved-policy", "zero"]].concat();
+        remove(&dir, &input, &options).unwrap();
+        let output = std::fs::read(dir.join("patched.bdmv")).unwrap();
+        assert_eq!(reserved(&output), [0, 0, 0]);
+        assert_eq!(output.len(), input.len());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn preserve_mtime_copies_the_input_time() {
+        let dir = testing::scratch_directory("preserve-mtime");
+        let input = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "JUMP_OBJECT 1"]]);
+        let path = dir.join("MovieObject.bdmv");
+        let mtime =
+            std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        let options = ["--region", "B", "--country", "US", "--preserve-mtime"];
+        // Write the input here rather than with `remove`, so it can be backdated first.
+        std::fs::write(&path, &input).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+        let output_path = dir.join("patched.bdmv");
+        run(Cli::try_parse_from(
+            ["bd-region", path.to_str().unwrap(), "remove"]
+                .into_iter()
+                .chain(options)
+                .chain([output_path.to_str().unwrap()]),
+        )
+        .unwrap())
+        .unwrap();
+        let modified = |path: &Path| std::fs::metadata(path).unwrap().modified().unwrap();
+        assert_eq!(modified(&output_path), mtime);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn from_list_patches_each_disc_and_reports_failures() {
+        let dir = testing::scratch_directory("from-list");
+        let library = dir.join("library");
+        let locked = testing::bytes(&[&["MOV gpr1, psr20", "NE gpr1, 2", "JUMP_OBJECT 1"]]);
+        let clean = testing::bytes(&[&["PLAY_PL 1"]]);
+        for (disc, bytes) in [("a", &locked), ("b", &clean)] {
+            std::fs::create_dir_all(library.join(disc).join("BDMV")).unwrap();
+            std::fs::write(library.join(disc).join("BDMV/MovieObject.bdmv"), bytes).unwrap();
+        }
+        let list = dir.join("list.txt");
+        std::fs::write(&list, "# discs to patch\na\n\nmissing\nb\n").unwrap();
+        let output = dir.join("patched");
+        let cli = Cli::try_parse_from([
+            "bd-region",
+            library.to_str().unwrap(),
+            "remove",
+            "--region",
+            "B",
+            "--country",
+            "US",
+            "--from-list",
+            list.to_str().unwrap(),
+            output.to_str().unwrap(),
+        ]);
+        let err = run(cli.unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "1 of 3 discs failed");
+        // The failure doesn't stop the discs after it.
+        let patched = MovieObjectFile::open(&output.join("a/BDMV/MovieObject.bdmv")).unwrap();
+        assert_eq!(
+            testing::assembly(&patched, 0),
+            ["MOV gpr1, 2", "NE gpr1, 2", "JUMP_OBJECT 1"]
+        );
+        assert_eq!(
+            std::fs::read(output.join("b/BDMV/MovieObject.bdmv")).unwrap(),
+            clean
+        );
+        assert!(!output.join("missing").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reference_disc_drives_the_patched_values() {
+        let dir = testing::scratch_directory("reference");
+        let reference = dir.join("reference.bdmv");
+        std::fs::write(
+            &reference,
+            testing::bytes(&[
+                &["MOV gpr1, psr20", "NE gpr1, 4", "JUMP_OBJECT 1"],
+                &["EQ psr19, 0x4a50"],
+            ]),
+        )
+        .unwrap();
+        let input = testing::bytes(&[&["MOV gpr1, psr20", "MOV gpr2, psr19"]]);
+        let options = ["--reference", reference.to_str().unwrap()];
+        remove(&dir, &input, &options).unwrap();
+        let output = MovieObjectFile::open(&dir.join("patched.bdmv")).unwrap();
+        // Region C, and 19024 is 0x4a50, "JP".
+        assert_eq!(
+            testing::assembly(&output, 0),
+            ["MOV gpr1, 4", "MOV gpr2, 19024"]
+        );
+        std::fs::remove_file(dir.join("patched.bdmv")).unwrap();
+        std::fs::write(
+            &reference,
+            testing::bytes(&[&["EQ psr20, 1", "EQ psr20, 2"]]),
+        )
+        .unwrap();
+        let err = remove(&dir, &input, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "reference disc {} compares the region against several values: 0x1, 0x2",
+                reference.display()
+            )
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn nop_patch_wildcard_covers_whole_object() {
+        let dir = testing::scratch_directory("wildcard");
+        let input = testing::bytes(&[
+            &["PLAY_PL 0"],
+            &["PLAY_PL 1"],
+            &["MOV gpr1, 1", "EQ gpr1, 2", "PLAY_PL 2", "TERMINATE"],
+        ]);
+        let options = ["--region", "B", "--country", "US", "--nop-patch", "2,*"];
+        remove(&dir, &input, &options).unwrap();
+        let output = MovieObjectFile::open(&dir.join("patched.bdmv")).unwrap();
+        assert_eq!(testing::assembly(&output, 1), ["PLAY_PL 1"]);
+        assert_eq!(testing::assembly(&output, 2), ["NOP"; 4]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rewrite_leaves_no_checks_in_output() {
+        let mut file = testing::file(&[&[
+            "MOV gpr1, psr20",
+            "NE gpr1, 2",
+            "JUMP_OBJECT 1",
+            "EQ psr19, 0x4a50",
+            "NOP",
+        ]]);
+        let patched = patch_commands(&mut file, &HashMap::new(), Some("US"), Some(Region::B));
+        assert_eq!(patched.unwrap(), 2);
+        let reparsed = MovieObjectFile::from_bytes(&file.serialize()).unwrap();
+        assert_eq!(locking_checks(&reparsed.region_checks()).count(), 0);
+    }
+
+    #[test]
+    fn explain_describes_each_gate_once() {
+        let file = testing::file(&[
+            &[
+                "MOV gpr1, psr20",
+                "NE gpr1, 2",
+                "JUMP_OBJECT 1",
+                "PLAY_PL 1",
+            ],
+            &["PLAY_PL 99", "TERMINATE"],
+        ]);
+        assert_eq!(
+            explain(&file, &file.region_checks()),
+            [
+                "Movie object 0 reads the player's region (PSR20) and, if it isn't region B, jumps to object 1 which plays something and then stops playback, probably a wrong region screen."
+            ]
+        );
+    }
+
+    #[test]
+    fn explain_handles_compare_at_end_of_object() {
+        let file = testing::file(&[&["MOV gpr2, 1", "EQ psr19, 0x4a50"]]);
+        assert_eq!(
+            explain(&file, &file.region_checks()),
+            [
+                "Movie object 0 reads the player's country (PSR19) and, if it is country JP, would run the next navigation command, but the compare is the last one."
+            ]
+        );
     }
 }